@@ -0,0 +1,142 @@
+use super::utils::encode_attribute_value;
+use anyhow::{bail, Context, Result};
+use bio_rascal::gff::{Annotation, Phase, Strand};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use uuid::Uuid;
+
+/// How the columns of a BED file are converted to an annotation
+pub struct BedFields {
+    /// Value of the `source` column
+    pub source: String,
+    /// Value of the `feature_type` column
+    pub feature_type: String,
+    /// Attribute used for the BED name (4th column)
+    pub name_attribute: String,
+    /// Attributes for the columns after the 6th, in order. Columns without
+    /// a name are ignored. The values are percent-encoded, commas included,
+    /// so lists like `blockSizes` are kept as a single value
+    pub columns: Vec<String>,
+}
+
+fn parse_bed_line(line: &str, fields: &BedFields) -> Result<Annotation> {
+    let values: Vec<&str> = line.trim_end().split('\t').map(|f| f.trim()).collect();
+    if values.len() < 3 {
+        bail!("Expected at least 3 columns, found {}", values.len());
+    }
+
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    if let Some(name) = values.get(3) {
+        if !name.is_empty() && *name != "." {
+            attributes.insert(fields.name_attribute.clone(), encode_attribute_value(name));
+        }
+    }
+    for (key, value) in fields.columns.iter().zip(values.iter().skip(6)) {
+        if !value.is_empty() {
+            attributes.insert(key.clone(), encode_attribute_value(value));
+        }
+    }
+
+    let mut annotation = Annotation {
+        seq_id: values[0].to_owned(),
+        source: fields.source.clone(),
+        feature_type: fields.feature_type.clone(),
+        start: values[1].parse().context("Parsing Start field failed")?,
+        end: values[2].parse().context("Parsing End field failed")?,
+        score: values.get(4).and_then(|value| value.parse().ok()).unwrap_or(0.),
+        strand: Strand::from_value(values.get(5).unwrap_or(&".")),
+        phase: Phase::from_value(".").context("Cannot parse Phase")?,
+        uid: Uuid::new_v4(),
+        attributes,
+        taxon_id: 0,
+    };
+    // BED is 0-based, half open
+    annotation.start += 1;
+
+    Ok(annotation)
+}
+
+/// Reads a BED file, returning an annotation, with a new `uid`, for each line.
+/// `track` and `browser` lines and comments are skipped.
+pub struct BedReader {
+    reader: BufReader<Box<dyn Read>>,
+    fields: BedFields,
+    line_number: usize,
+}
+
+impl BedReader {
+    pub fn from_reader(reader: Box<dyn Read>, fields: BedFields) -> Self {
+        BedReader {
+            reader: BufReader::new(reader),
+            fields,
+            line_number: 0,
+        }
+    }
+}
+
+impl Iterator for BedReader {
+    type Item = Result<Annotation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = String::new();
+
+        loop {
+            buffer.clear();
+            self.line_number += 1;
+            match self.reader.read_line(&mut buffer) {
+                Err(err) => {
+                    return Some(Err(err).with_context(|| format!("Cannot read line {}", self.line_number)))
+                }
+                Ok(0) => return None,
+                Ok(_) if buffer.starts_with('#')
+                    || buffer.starts_with("track")
+                    || buffer.starts_with("browser")
+                    || buffer.trim().is_empty() =>
+                {
+                    continue
+                }
+                Ok(_) => {
+                    let line_number = self.line_number;
+                    return Some(parse_bed_line(&buffer, &self.fields).with_context(|| {
+                        format!("Line {}: {:?}", line_number, buffer.trim_end())
+                    }));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::utils::decode_attribute_value;
+    use super::*;
+
+    #[test]
+    fn values_are_encoded() {
+        let fields = BedFields {
+            source: ".".into(),
+            feature_type: "region".into(),
+            name_attribute: "Name".into(),
+            columns: [
+                "thickStart",
+                "thickEnd",
+                "itemRgb",
+                "blockCount",
+                "blockSizes",
+                "blockStarts",
+            ]
+            .map(String::from)
+            .to_vec(),
+        };
+        let annotation = parse_bed_line(
+            "chr1\t0\t40\tgene=a;b\t0\t+\t0\t40\t0\t2\t10,20,\t0,20,\n",
+            &fields,
+        )
+        .unwrap();
+        assert_eq!(annotation.start, 1);
+        assert_eq!(annotation.attributes["Name"], "gene%3Da%3Bb");
+        assert_eq!(annotation.attributes["blockSizes"], "10%2C20%2C");
+        assert_eq!(decode_attribute_value(&annotation.attributes["blockStarts"]), "0,20,");
+        assert_eq!(annotation.attributes["blockCount"], "2");
+    }
+}
@@ -1,14 +1,14 @@
+use super::super::gff::{GffFile, GffWriter};
 use super::super::utils::{file_or_stdin, file_or_stdout, read_uid_file};
 use super::AddCommand;
 use anyhow::{Context, Result};
-use bio_rascal::gff::GffReader;
 use log::info;
 use std::collections::{HashMap, HashSet};
 
 pub fn add_command(options: &AddCommand) -> Result<()> {
     // first check the input and output files
     let input_file = file_or_stdin(&options.input_file)?;
-    let mut output_file = file_or_stdout(&options.output_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
 
     // Stores the key:value changes into a HashMap
     let attributes: HashMap<String, String> =
@@ -18,11 +18,11 @@ pub fn add_command(options: &AddCommand) -> Result<()> {
     // Makes the set for UIDs
     let uid_set: HashSet<String> = read_uid_file(&options.uid_file)?;
 
-    // Init the reader as mutable
-    let reader = GffReader::from_reader(input_file);
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
 
     // Using a while loop, since None will be the end
-    for mut annotation in reader {
+    for mut annotation in gff_file.annotations() {
         // If the uid_set is empty or the UID is contained, modify the
         // annotation
         if uid_set.is_empty() || uid_set.contains(&annotation.uid.to_string()) {
@@ -42,8 +42,8 @@ pub fn add_command(options: &AddCommand) -> Result<()> {
             }
         }
         // Writes to the output file
-        writeln!(output_file, "{}", annotation.to_string())?;
+        writer.write(&annotation)?;
     }
 
-    Ok(())
+    writer.finish()
 }
@@ -0,0 +1,143 @@
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
+use super::BedCommand;
+use anyhow::Result;
+use bio_rascal::gff::{Annotation, GffReader};
+use itertools::Itertools;
+use log::info;
+use std::collections::HashMap;
+
+/// Returns the first 6 BED columns for an annotation, using 0-based half
+/// open coordinates. A start of 0, invalid in GFF, is written as 0. The
+/// score is rounded to an integer between 0 and 1000, as BED requires
+fn bed6_fields(annotation: &Annotation, name_field: &str) -> Vec<String> {
+    vec![
+        annotation.seq_id.clone(),
+        annotation.start.saturating_sub(1).to_string(),
+        annotation.end.to_string(),
+        get_field_value(annotation, name_field).unwrap_or_else(|| ".".into()),
+        (annotation.score.round().clamp(0., 1000.) as u64).to_string(),
+        annotation.strand.to_string(),
+    ]
+}
+
+/// Returns the BED12 line of a transcript, with blocks from its children
+/// and the thick region from its coding parts. Without coding parts
+/// the thick region is empty.
+fn bed12_line(transcript: &Annotation, blocks: &[Annotation], coding: &[Annotation], name_field: &str) -> String {
+    let chrom_start = transcript.start.saturating_sub(1);
+    let mut fields = bed6_fields(transcript, name_field);
+
+    let (thick_start, thick_end) = match (
+        coding.iter().map(|annotation| annotation.start).min(),
+        coding.iter().map(|annotation| annotation.end).max(),
+    ) {
+        (Some(start), Some(end)) => (start.saturating_sub(1), end),
+        _ => (chrom_start, chrom_start),
+    };
+    fields.push(thick_start.to_string());
+    fields.push(thick_end.to_string());
+    fields.push("0".into());
+
+    let blocks: Vec<_> = blocks
+        .iter()
+        .map(|annotation| (annotation.start.saturating_sub(1), annotation.end))
+        .sorted()
+        .collect();
+    fields.push(blocks.len().to_string());
+    fields.push(blocks.iter().map(|(start, end)| format!("{},", end - start)).join(""));
+    fields.push(
+        blocks
+            .iter()
+            .map(|(start, _)| format!("{},", start.saturating_sub(chrom_start)))
+            .join(""),
+    );
+
+    fields.join("\t")
+}
+
+pub fn bed_command(options: &BedCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let mut output_file = file_or_stdout(&options.output_file)?;
+
+    let reader = GffReader::from_reader(input_file);
+
+    let mut count = 0;
+    if !options.bed12 {
+        for annotation in reader {
+            writeln!(output_file, "{}", bed6_fields(&annotation, &options.name).join("\t"))?;
+            count += 1;
+        }
+        info!("Written {} BED6 lines", count);
+        return Ok(());
+    }
+
+    // Annotations are kept in memory to group the children by Parent
+    let mut annotations: Vec<Annotation> = Vec::new();
+    let mut blocks: HashMap<String, Vec<Annotation>> = HashMap::new();
+    let mut coding: HashMap<String, Vec<Annotation>> = HashMap::new();
+    for annotation in reader {
+        let group = if annotation.feature_type == options.block_type {
+            &mut blocks
+        } else if annotation.feature_type == options.thick_type {
+            &mut coding
+        } else {
+            annotations.push(annotation);
+            continue;
+        };
+        if let Some(parents) = annotation.get_attr("Parent") {
+            for parent in parents.split(',') {
+                group.entry(parent.to_string()).or_default().push(annotation.clone());
+            }
+        }
+    }
+
+    for transcript in annotations {
+        let id = match transcript.get_attr("ID") {
+            None => continue,
+            Some(id) => id,
+        };
+        let transcript_blocks = match blocks.get(&id) {
+            None => continue,
+            Some(transcript_blocks) => transcript_blocks,
+        };
+        let transcript_coding = coding.get(&id).map(Vec::as_slice).unwrap_or_default();
+        writeln!(
+            output_file,
+            "{}",
+            bed12_line(&transcript, transcript_blocks, transcript_coding, &options.name)
+        )?;
+        count += 1;
+    }
+    info!("Written {} BED12 lines", count);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bio_rascal::gff::{Phase, Strand};
+    use uuid::Uuid;
+
+    #[test]
+    fn scores_are_integers() {
+        let mut annotation = Annotation {
+            seq_id: "chr1".into(),
+            source: ".".into(),
+            feature_type: "gene".into(),
+            start: 1,
+            end: 100,
+            score: 35.2,
+            strand: Strand::from_value("+"),
+            phase: Phase::from_value(".").unwrap(),
+            uid: Uuid::new_v4(),
+            attributes: HashMap::new(),
+            taxon_id: 0,
+        };
+        for (score, expected) in [(35.2, "35"), (35.7, "36"), (-2., "0"), (2500., "1000"), (0., "0")] {
+            annotation.score = score;
+            assert_eq!(bed6_fields(&annotation, "uid")[4], expected);
+        }
+    }
+}
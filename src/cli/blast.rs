@@ -0,0 +1,218 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{annotation_key, file_or_stdin, file_or_stdout};
+use super::{BlastCommand, BlastSort};
+use anyhow::{bail, Context, Result};
+use bio_rascal::io::open_file;
+use log::{info, warn};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+
+/// A hit in the BLAST tabular output, with the values used to choose the
+/// best one
+struct Hit {
+    subject: String,
+    identity: f64,
+    evalue: f64,
+    bitscore: f64,
+    /// E-value as written in the file, to keep the notation
+    evalue_text: String,
+}
+
+/// Position of the columns used, in the format passed
+struct Columns {
+    query: usize,
+    subject: usize,
+    identity: usize,
+    evalue: usize,
+    bitscore: usize,
+    qstart: Option<usize>,
+    qend: Option<usize>,
+    qlen: Option<usize>,
+    qcovhsp: Option<usize>,
+}
+
+impl Columns {
+    fn from_fields(fields: &[String]) -> Result<Self> {
+        let position = |name: &str| fields.iter().position(|field| field == name);
+        let required = |name: &str| match position(name) {
+            None => bail!("Column '{}' is required in the BLAST format", name),
+            Some(index) => Ok(index),
+        };
+        Ok(Columns {
+            query: required("qseqid")?,
+            subject: required("sseqid")?,
+            identity: required("pident")?,
+            evalue: required("evalue")?,
+            bitscore: required("bitscore")?,
+            qstart: position("qstart"),
+            qend: position("qend"),
+            qlen: position("qlen"),
+            qcovhsp: position("qcovhsp"),
+        })
+    }
+
+    /// Returns true if the query coverage can be computed
+    fn has_coverage(&self) -> bool {
+        self.qcovhsp.is_some()
+            || (self.qstart.is_some() && self.qend.is_some() && self.qlen.is_some())
+    }
+
+    /// Query coverage of the hit, as a percentage
+    fn coverage(&self, values: &[&str]) -> Result<f64> {
+        if let Some(index) = self.qcovhsp {
+            return Ok(values[index].parse()?);
+        }
+        match (self.qstart, self.qend, self.qlen) {
+            (Some(qstart), Some(qend), Some(qlen)) => {
+                let qstart: f64 = values[qstart].parse()?;
+                let qend: f64 = values[qend].parse()?;
+                let qlen: f64 = values[qlen].parse()?;
+                Ok(((qend - qstart).abs() + 1.) / qlen * 100.)
+            }
+            _ => bail!("Cannot compute the query coverage"),
+        }
+    }
+}
+
+/// Compares two hits, `Ordering::Greater` is the better one
+fn compare_hits(a: &Hit, b: &Hit, sort_by: BlastSort) -> Ordering {
+    let bitscore = a.bitscore.total_cmp(&b.bitscore);
+    let evalue = b.evalue.total_cmp(&a.evalue);
+    match sort_by {
+        BlastSort::Bitscore => bitscore.then(evalue),
+        BlastSort::Evalue => evalue.then(bitscore),
+    }
+}
+
+/// Reads the BLAST tabular output, keeping the best hit for each query
+/// among the ones passing the thresholds
+fn read_best_hits(options: &BlastCommand) -> Result<HashMap<String, Hit>> {
+    let columns = Columns::from_fields(&options.fields)?;
+    if options.min_coverage > 0. && !columns.has_coverage() {
+        bail!("The query coverage needs either qcovhsp or qstart, qend and qlen in the format");
+    }
+    let file_handle = open_file(&options.blast_file)?;
+
+    let mut best_hits: HashMap<String, Hit> = HashMap::new();
+    let mut count = 0;
+    let mut passed = 0;
+    for (index, line) in file_handle.lines().enumerate() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let values: Vec<&str> = line.split('\t').collect();
+        if values.len() < options.fields.len() {
+            bail!(
+                "Line {} has {} columns, expected {}",
+                index + 1,
+                values.len(),
+                options.fields.len()
+            );
+        }
+        count += 1;
+        let parse = |column: usize| -> Result<f64> {
+            values[column]
+                .trim()
+                .parse()
+                .with_context(|| format!("Cannot parse '{}' at line {}", values[column], index + 1))
+        };
+        let hit = Hit {
+            subject: values[columns.subject].to_string(),
+            identity: parse(columns.identity)?,
+            evalue: parse(columns.evalue)?,
+            bitscore: parse(columns.bitscore)?,
+            evalue_text: values[columns.evalue].trim().to_string(),
+        };
+        if hit.identity < options.min_identity {
+            continue;
+        }
+        if matches!(options.max_evalue, Some(max_evalue) if hit.evalue > max_evalue) {
+            continue;
+        }
+        if options.min_coverage > 0. && columns.coverage(&values)? < options.min_coverage {
+            continue;
+        }
+        passed += 1;
+        match best_hits.get_mut(values[columns.query]) {
+            Some(best_hit) => {
+                if compare_hits(&hit, best_hit, options.sort_by) == Ordering::Greater {
+                    *best_hit = hit;
+                }
+            }
+            None => _ = best_hits.insert(values[columns.query].to_string(), hit),
+        }
+    }
+
+    info!(
+        "Read {} hit(s), {} passing the thresholds, for {} queries",
+        count,
+        passed,
+        best_hits.len()
+    );
+
+    Ok(best_hits)
+}
+
+pub fn blast_command(options: &BlastCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    info!(
+        "Reading BLAST hits from file {}",
+        options.blast_file.display()
+    );
+    let best_hits = read_best_hits(options).context("Cannot read the BLAST file")?;
+
+    if options.prodigal_gene {
+        info!("Using key from Prodigal sequences")
+    } else {
+        info!("Using '{}' as key", options.key);
+    }
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut matched: HashSet<String> = HashSet::new();
+    let mut count = 0;
+    let mut edited = 0;
+    for mut annotation in gff_file.annotations() {
+        count += 1;
+        let key_value = annotation_key(&annotation, &options.key, options.prodigal_gene);
+        match key_value.and_then(|key_value| best_hits.get_key_value(&key_value)) {
+            Some((query, hit)) => {
+                let values = [
+                    (&options.subject_attribute, hit.subject.clone()),
+                    (&options.identity_attribute, hit.identity.to_string()),
+                    (&options.evalue_attribute, hit.evalue_text.clone()),
+                ];
+                for (attribute, value) in values {
+                    annotation.attributes.insert(attribute.clone(), value);
+                }
+                matched.insert(query.clone());
+                edited += 1;
+            }
+            None => {
+                if options.only_edited {
+                    continue;
+                }
+            }
+        }
+        writer.write(&annotation)?;
+    }
+
+    info!(
+        "Added the best hit to {} of {} annotation(s)",
+        edited, count
+    );
+    if matched.len() < best_hits.len() {
+        warn!(
+            "{} queries with hits not found in the GFF",
+            best_hits.len() - matched.len()
+        );
+    }
+
+    writer.finish()
+}
@@ -0,0 +1,169 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::intervals::IntervalIndex;
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
+use super::ClosestCommand;
+use anyhow::Result;
+use bio_rascal::gff::Annotation;
+use itertools::Itertools;
+use log::info;
+use std::io::Write;
+
+/// Nearest feature on one side of an annotation, with the number of bases
+/// between them, negative if upstream
+struct Neighbour<'a> {
+    feature: &'a Annotation,
+    distance: i64,
+}
+
+/// Returns the upstream and downstream neighbours of an annotation, with the
+/// options passed (feature type and strand)
+fn find_neighbours<'a>(
+    index: &'a IntervalIndex,
+    annotation: &Annotation,
+    options: &ClosestCommand,
+) -> (Option<Neighbour<'a>>, Option<Neighbour<'a>>) {
+    let intervals = match index.get(&annotation.seq_id) {
+        None => return (None, None),
+        Some(intervals) => intervals,
+    };
+    let strand = annotation.strand.to_string();
+    let is_neighbour = |feature: &&Annotation| {
+        let same_type = match &options.feature_type {
+            None => true,
+            Some(feature_type) => &feature.feature_type == feature_type,
+        };
+        same_type && (!options.same_strand || feature.strand.to_string() == strand)
+    };
+
+    let left = intervals
+        .left_of(annotation.start)
+        .find(is_neighbour)
+        .map(|feature| Neighbour {
+            feature,
+            distance: (annotation.start - feature.end - 1) as i64,
+        });
+    let right = intervals
+        .right_of(annotation.end)
+        .find(is_neighbour)
+        .map(|feature| Neighbour {
+            feature,
+            distance: (feature.start - annotation.end - 1) as i64,
+        });
+
+    // annotations without a strand are considered on the forward one
+    let (upstream, downstream) = match strand.as_str() {
+        "-" => (right, left),
+        _ => (left, right),
+    };
+    (
+        upstream.map(|neighbour| Neighbour {
+            distance: -neighbour.distance,
+            ..neighbour
+        }),
+        downstream,
+    )
+}
+
+/// Returns the field of the neighbour and its distance, empty if there is
+/// no neighbour
+fn neighbour_values(neighbour: &Option<Neighbour>, name_field: &str) -> (String, String) {
+    match neighbour {
+        None => (String::new(), String::new()),
+        Some(neighbour) => (
+            get_field_value(neighbour.feature, name_field).unwrap_or_default(),
+            neighbour.distance.to_string(),
+        ),
+    }
+}
+
+/// Writes a table with the neighbours of each annotation
+fn write_table(
+    annotations: &[Annotation],
+    index: &IntervalIndex,
+    options: &ClosestCommand,
+    mut output_file: Box<dyn Write>,
+) -> Result<()> {
+    if options.header {
+        writeln!(
+            output_file,
+            "#{}\tupstream\tupstream_distance\tdownstream\tdownstream_distance",
+            options.fields.iter().join("\t")
+        )?;
+    }
+
+    for annotation in annotations {
+        let (upstream, downstream) = find_neighbours(index, annotation, options);
+        let (upstream_name, upstream_distance) = neighbour_values(&upstream, &options.name_field);
+        let (downstream_name, downstream_distance) =
+            neighbour_values(&downstream, &options.name_field);
+        let values = options
+            .fields
+            .iter()
+            .map(|field| get_field_value(annotation, field).unwrap_or_default())
+            .join("\t");
+        writeln!(
+            output_file,
+            "{}\t{}\t{}\t{}\t{}",
+            values, upstream_name, upstream_distance, downstream_name, downstream_distance
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn closest_command(options: &ClosestCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    // the annotations are all read, since they can be the neighbours too
+    let gff_file = GffFile::from_reader(input_file);
+    let annotations: Vec<Annotation> = gff_file.annotations().collect();
+    info!("Read {} annotations", annotations.len());
+
+    let index = match &options.other_file {
+        Some(other_file) => IntervalIndex::from_file(other_file, options.bed)?,
+        None => IntervalIndex::new(annotations.iter().cloned()),
+    };
+
+    if options.table {
+        return write_table(&annotations, &index, options, output_file);
+    }
+
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut upstream_count = 0;
+    let mut downstream_count = 0;
+    for (position, annotation) in annotations.iter().enumerate() {
+        let (upstream, downstream) = find_neighbours(&index, annotation, options);
+        if upstream.is_some() {
+            upstream_count += 1;
+        }
+        if downstream.is_some() {
+            downstream_count += 1;
+        }
+        let (upstream_name, upstream_distance) = neighbour_values(&upstream, &options.name_field);
+        let (downstream_name, downstream_distance) =
+            neighbour_values(&downstream, &options.name_field);
+
+        let mut annotation = annotation.clone();
+        for (attribute, value) in [
+            ("upstream", upstream_name),
+            ("upstream_distance", upstream_distance),
+            ("downstream", downstream_name),
+            ("downstream_distance", downstream_distance),
+        ] {
+            if !value.is_empty() {
+                annotation.attributes.insert(attribute.to_string(), value);
+            }
+        }
+        writer.write_at(&annotation, position)?;
+    }
+
+    info!(
+        "Found the upstream neighbour of {} and the downstream one of {} annotation(s)",
+        upstream_count, downstream_count
+    );
+
+    writer.finish()
+}
@@ -0,0 +1,158 @@
+use super::super::fasta::read_lengths;
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
+use super::ComplementCommand;
+use anyhow::{Context, Result};
+use bio_rascal::gff::{Annotation, Phase, Strand};
+use bio_rascal::io::open_file;
+use log::{info, warn};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+/// Returns the orientation of the flanking features, if both are stranded
+fn orientation(left: &str, right: &str) -> Option<&'static str> {
+    match (left, right) {
+        ("+", "-") => Some("convergent"),
+        ("-", "+") => Some("divergent"),
+        ("+", "+") | ("-", "-") => Some("co-directional"),
+        _ => None,
+    }
+}
+
+/// Makes the annotation for an intergenic region, with the attributes of
+/// the flanking features. The taxon_id is kept if the same for both.
+fn intergenic_region(
+    seq_id: &str,
+    start: u64,
+    end: u64,
+    left: Option<&Annotation>,
+    right: Option<&Annotation>,
+    options: &ComplementCommand,
+) -> Result<Annotation> {
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    for (side, flank) in [("left", left), ("right", right)] {
+        if let Some(flank) = flank {
+            if let Some(name) = get_field_value(flank, &options.name_field) {
+                attributes.insert(format!("{}_feature", side), name);
+            }
+            attributes.insert(format!("{}_strand", side), flank.strand.to_string());
+        }
+    }
+    if let (Some(left), Some(right)) = (left, right) {
+        if let Some(orientation) = orientation(&left.strand.to_string(), &right.strand.to_string())
+        {
+            attributes.insert("orientation".into(), orientation.into());
+        }
+    }
+
+    let taxon_id = match (left, right) {
+        (Some(left), Some(right)) if left.taxon_id == right.taxon_id => left.taxon_id,
+        (Some(flank), None) | (None, Some(flank)) => flank.taxon_id,
+        _ => 0,
+    };
+
+    Ok(Annotation {
+        seq_id: seq_id.to_string(),
+        source: ".".into(),
+        feature_type: "intergenic".into(),
+        start,
+        end,
+        score: 0.,
+        strand: Strand::from_value("."),
+        phase: Phase::from_value(".").context("Cannot parse Phase")?,
+        uid: Uuid::new_v4(),
+        attributes,
+        taxon_id,
+    })
+}
+
+pub fn complement_command(options: &ComplementCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    if !options.feature_types.is_empty() {
+        info!("Using feature types: {}", options.feature_types.join(", "));
+    }
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut groups: HashMap<String, Vec<Annotation>> = HashMap::new();
+    for annotation in gff_file.annotations() {
+        if options.feature_types.is_empty()
+            || options.feature_types.contains(&annotation.feature_type)
+        {
+            groups
+                .entry(annotation.seq_id.clone())
+                .or_default()
+                .push(annotation);
+        }
+    }
+
+    let mut lengths = gff_file.sequence_regions();
+    info!(
+        "Found the length of {} sequence(s) in the directives",
+        lengths.len()
+    );
+    if let Some(file_name) = &options.lengths {
+        info!("Reading sequence lengths from file {}", file_name.display());
+        lengths.extend(read_lengths(open_file(file_name)?).context("Cannot read the lengths")?);
+    }
+
+    let seq_ids: BTreeSet<&String> = groups.keys().chain(lengths.keys()).collect();
+    let mut regions: Vec<Annotation> = Vec::new();
+    let mut no_length = 0;
+    for seq_id in seq_ids {
+        let mut annotations: Vec<&Annotation> = groups
+            .get(seq_id)
+            .map(|annotations| annotations.iter().collect())
+            .unwrap_or_default();
+        annotations.sort_by_key(|annotation| (annotation.start, annotation.end));
+
+        // the left flank is the feature ending last among the ones before
+        let mut left: Option<&Annotation> = None;
+        for annotation in annotations {
+            let start = left.map_or(1, |left| left.end + 1);
+            if annotation.start > start && annotation.start - start >= options.min_length {
+                regions.push(intergenic_region(
+                    seq_id,
+                    start,
+                    annotation.start - 1,
+                    left,
+                    Some(annotation),
+                    options,
+                )?);
+            }
+            match left {
+                Some(left) if annotation.end <= left.end => {}
+                _ => left = Some(annotation),
+            }
+        }
+
+        match lengths.get(seq_id) {
+            None => no_length += 1,
+            Some(length) => {
+                let start = left.map_or(1, |left| left.end + 1);
+                if *length >= start && length - start + 1 >= options.min_length {
+                    regions.push(intergenic_region(
+                        seq_id, start, *length, left, None, options,
+                    )?);
+                }
+            }
+        }
+    }
+
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+    for region in &regions {
+        writer.write(region)?;
+    }
+
+    info!("Written {} intergenic region(s)", regions.len());
+    if no_length > 0 {
+        warn!(
+            "{} sequence(s) without a length, the regions at their end are not written",
+            no_length
+        );
+    }
+
+    writer.finish()
+}
@@ -0,0 +1,193 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{annotation_key, encode_attribute_value, file_or_stdin, file_or_stdout};
+use super::EggnogCommand;
+use anyhow::{bail, Context, Result};
+use bio_rascal::io::open_file;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::Path;
+
+/// Columns of the eggNOG-mapper output that contain comma separated lists
+const LIST_COLUMNS: [&str; 13] = [
+    "eggNOG_OGs",
+    "GOs",
+    "EC",
+    "KEGG_ko",
+    "KEGG_Pathway",
+    "KEGG_Module",
+    "KEGG_Reaction",
+    "KEGG_rclass",
+    "BRITE",
+    "KEGG_TC",
+    "CAZy",
+    "BiGG_Reaction",
+    "PFAMs",
+];
+
+/// Values of the requested columns for each query, missing values (`-`)
+/// are not included
+type EmapperTable = HashMap<String, Vec<(String, String)>>;
+
+/// Cleans a value from the eggNOG-mapper table, returning `None` if it's
+/// missing. Lists are trimmed and duplicated values removed, to be used as
+/// GFF multi-values. Values are percent-encoded, since descriptions can
+/// contain `;` or `,`.
+fn clean_value(column: &str, value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value == "-" {
+        return None;
+    }
+    if !LIST_COLUMNS.contains(&column) {
+        return Some(encode_attribute_value(value));
+    }
+    let mut seen = HashSet::new();
+    let values: Vec<String> = value
+        .split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty() && *value != "-" && seen.insert(*value))
+        .map(encode_attribute_value)
+        .collect();
+    match values.is_empty() {
+        true => None,
+        false => Some(values.join(",")),
+    }
+}
+
+/// Reads the `.emapper.annotations` file, keeping only the columns
+/// requested. The header is the last line starting with `#` before the
+/// table (`#query` or `#query_name`), other comment lines are skipped.
+fn read_emapper<P: AsRef<Path>>(file_name: P, columns: &[String]) -> Result<EmapperTable> {
+    let file_handle = open_file(file_name.as_ref())?;
+
+    let mut header: Option<Vec<String>> = None;
+    let mut indices: Vec<(usize, String)> = Vec::with_capacity(columns.len());
+    let mut table = EmapperTable::new();
+
+    for line in file_handle.lines() {
+        let line = line?;
+        if line.starts_with("##") || line.trim().is_empty() {
+            continue;
+        }
+        if let Some(names) = line.strip_prefix('#') {
+            header = Some(
+                names
+                    .split('\t')
+                    .map(|name| name.trim().to_string())
+                    .collect(),
+            );
+            continue;
+        }
+        let names = match &header {
+            None => bail!("No header found in the eggNOG-mapper file"),
+            Some(names) => names,
+        };
+        if indices.is_empty() {
+            for column in columns {
+                match names.iter().position(|name| name == column) {
+                    None => bail!(
+                        "Column '{}' not found, available columns: {}",
+                        column,
+                        names.join(", ")
+                    ),
+                    Some(index) => indices.push((index, column.clone())),
+                }
+            }
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let values = indices
+            .iter()
+            .filter_map(|(index, column)| {
+                let value = clean_value(column, fields.get(*index)?)?;
+                Some((column.clone(), value))
+            })
+            .collect();
+        table.insert(fields[0].to_string(), values);
+    }
+
+    info!("Read {} hit(s) from the eggNOG-mapper file", table.len());
+
+    Ok(table)
+}
+
+pub fn eggnog_command(options: &EggnogCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    info!(
+        "Reading eggNOG-mapper annotations from file {}",
+        options.emapper_file.display()
+    );
+    let table = read_emapper(&options.emapper_file, &options.columns)
+        .context("Cannot read the eggNOG-mapper file")?;
+
+    if options.prodigal_gene {
+        info!("Using key from Prodigal sequences")
+    } else {
+        info!("Using '{}' as key", options.key);
+    }
+    info!("Adding columns: {}", options.columns.join(", "));
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut matched: HashSet<String> = HashSet::new();
+    let mut count = 0;
+    let mut edited = 0;
+    for mut annotation in gff_file.annotations() {
+        count += 1;
+        let key_value = annotation_key(&annotation, &options.key, options.prodigal_gene);
+        match key_value.and_then(|key_value| table.get_key_value(&key_value)) {
+            Some((query, values)) => {
+                for (column, value) in values {
+                    annotation.attributes.insert(column.clone(), value.clone());
+                }
+                matched.insert(query.clone());
+                edited += 1;
+            }
+            None => {
+                if options.only_edited {
+                    continue;
+                }
+            }
+        }
+        writer.write(&annotation)?;
+    }
+
+    info!(
+        "Added attributes to {} of {} annotation(s), using {} of {} hit(s)",
+        edited,
+        count,
+        matched.len(),
+        table.len()
+    );
+    if matched.len() < table.len() {
+        warn!(
+            "{} eggNOG-mapper hit(s) not found in the GFF",
+            table.len() - matched.len()
+        );
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_cleaned_and_encoded() {
+        assert_eq!(clean_value("Description", "-"), None);
+        assert_eq!(
+            clean_value("Description", "Kinase; binds ATP, Mg=2"),
+            Some("Kinase%3B binds ATP%2C Mg%3D2".into())
+        );
+        assert_eq!(
+            clean_value("KEGG_ko", "ko:K00001, ko:K00002,ko:K00001,-"),
+            Some("ko:K00001,ko:K00002".into())
+        );
+        assert_eq!(clean_value("PFAMs", "-,-"), None);
+    }
+}
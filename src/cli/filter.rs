@@ -0,0 +1,501 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
+use super::FilterCommand;
+use anyhow::{bail, Context, Result};
+use bio_rascal::gff::Annotation;
+use log::info;
+use regex::Regex;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Comparison operators supported in an expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Regex(String),
+    Cmp(CmpOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+/// Value on the right side of a comparison
+#[derive(Debug)]
+enum Value {
+    Str(String),
+    Number(f64),
+    Regex(Regex),
+}
+
+/// Field used in an expression, either a built-in field (falling back to
+/// the attributes) or, with the `attributes.` prefix, only an attribute
+#[derive(Debug, PartialEq)]
+enum Field {
+    Any(String),
+    Attribute(String),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Self {
+        match name.strip_prefix("attributes.") {
+            Some(attribute) => Field::Attribute(attribute.to_string()),
+            None => Field::Any(name.to_string()),
+        }
+    }
+
+    fn value(&self, annotation: &Annotation) -> Option<String> {
+        match self {
+            Field::Any(name) => get_field_value(annotation, name),
+            Field::Attribute(name) => annotation.attributes.get(name).cloned(),
+        }
+    }
+}
+
+/// Parsed expression, evaluated for each annotation
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Has(Field),
+    Compare(Field, CmpOp, Value),
+}
+
+/// Reads a quoted string or a regex, the `delimiter` can be escaped
+/// with a backslash. For regexes other escapes are kept as they are.
+fn read_delimited(
+    chars: &mut Peekable<CharIndices>,
+    delimiter: char,
+    keep_escapes: bool,
+    position: usize,
+) -> Result<String> {
+    let mut value = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, next)) if next == delimiter => value.push(next),
+                Some((_, next)) => {
+                    if keep_escapes {
+                        value.push('\\');
+                    }
+                    value.push(next);
+                }
+                None => break,
+            },
+            c if c == delimiter => return Ok(value),
+            c => value.push(c),
+        }
+    }
+    bail!("Unterminated {} starting at position {}", delimiter, position)
+}
+
+/// Splits the expression in tokens, with their position
+fn tokenize(expression: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '"' | '\'' => Token::Str(read_delimited(&mut chars, c, false, position)?),
+            '/' => Token::Regex(read_delimited(&mut chars, '/', true, position)?),
+            '&' | '|' => match chars.next() {
+                Some((_, next)) if next == c => {
+                    if c == '&' {
+                        Token::And
+                    } else {
+                        Token::Or
+                    }
+                }
+                _ => bail!("Expected '{}{}' at position {}", c, c, position),
+            },
+            '=' | '!' | '<' | '>' => {
+                let next = chars.peek().map(|(_, next)| *next);
+                let op = match (c, next) {
+                    ('=', Some('=')) => Token::Cmp(CmpOp::Eq),
+                    ('=', Some('~')) => Token::Cmp(CmpOp::Match),
+                    ('!', Some('=')) => Token::Cmp(CmpOp::Ne),
+                    ('!', Some('~')) => Token::Cmp(CmpOp::NotMatch),
+                    ('<', Some('=')) => Token::Cmp(CmpOp::Le),
+                    ('>', Some('=')) => Token::Cmp(CmpOp::Ge),
+                    ('<', _) => Token::Cmp(CmpOp::Lt),
+                    ('>', _) => Token::Cmp(CmpOp::Gt),
+                    ('!', _) => Token::Not,
+                    _ => bail!("Unknown operator at position {}", position),
+                };
+                // two characters operators
+                if !matches!(op, Token::Not | Token::Cmp(CmpOp::Lt) | Token::Cmp(CmpOp::Gt)) {
+                    chars.next();
+                }
+                op
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut value = String::from(c);
+                while let Some((_, next)) = chars.peek() {
+                    if next.is_ascii_digit() || matches!(next, '.' | 'e' | 'E' | '-' | '+') {
+                        value.push(*next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                Token::Number(
+                    value
+                        .parse()
+                        .with_context(|| format!("Cannot parse number at position {}", position))?,
+                )
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut value = String::from(c);
+                while let Some((_, next)) = chars.peek() {
+                    if next.is_alphanumeric() || matches!(next, '_' | '.') {
+                        value.push(*next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                match value.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Ident(value),
+                }
+            }
+            _ => bail!("Unexpected character '{}' at position {}", c, position),
+        };
+        tokens.push((position, token));
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser for the expression, the precedence from
+/// lowest to highest is `||`, `&&`, `!`
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// Length of the expression, used as position of the end
+    length: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Position in the expression of the last token returned by `next`
+    fn last_position(&self) -> usize {
+        match self.tokens.get(self.position.saturating_sub(1)) {
+            Some((position, _)) if self.position <= self.tokens.len() => *position,
+            _ => self.length,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            token => bail!(
+                "Expected {:?} at position {}, found {:?}",
+                expected,
+                self.last_position(),
+                token
+            ),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if name == "has" && self.peek() == Some(&Token::LParen) => {
+                self.next();
+                let field = match self.next() {
+                    Some(Token::Ident(field)) => Field::from_name(&field),
+                    token => bail!(
+                        "Expected a field name in has() at position {}, found {:?}",
+                        self.last_position(),
+                        token
+                    ),
+                };
+                self.expect(Token::RParen)?;
+                Ok(Expr::Has(field))
+            }
+            Some(Token::Ident(name)) => {
+                let field = Field::from_name(&name);
+                let op = match self.next() {
+                    Some(Token::Cmp(op)) => op,
+                    token => bail!(
+                        "Expected a comparison after '{}' at position {}, found {:?}",
+                        name,
+                        self.last_position(),
+                        token
+                    ),
+                };
+                let value = match (op, self.next()) {
+                    (CmpOp::Match | CmpOp::NotMatch, Some(Token::Regex(value) | Token::Str(value))) => {
+                        Value::Regex(
+                            Regex::new(&value)
+                                .with_context(|| format!("Invalid regular expression: {}", value))?,
+                        )
+                    }
+                    (CmpOp::Match | CmpOp::NotMatch, token) => bail!(
+                        "Expected a regular expression after '{}' at position {}, found {:?}",
+                        name,
+                        self.last_position(),
+                        token
+                    ),
+                    (_, Some(Token::Str(value))) => Value::Str(value),
+                    (_, Some(Token::Number(value))) => Value::Number(value),
+                    (_, token) => bail!(
+                        "Expected a string or number after '{}' at position {}, found {:?}",
+                        name,
+                        self.last_position(),
+                        token
+                    ),
+                };
+                Ok(Expr::Compare(field, op, value))
+            }
+            token => bail!(
+                "Unexpected token {:?} at position {}",
+                token,
+                self.last_position()
+            ),
+        }
+    }
+}
+
+/// Parses an expression like
+/// `feature_type == "CDS" && length >= 300 && attributes.product =~ /kinase/`
+fn parse_expression(expression: &str) -> Result<Expr> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        length: expression.len(),
+    };
+    let expr = parser.parse_or()?;
+    if let Some((position, token)) = parser.tokens.get(parser.position) {
+        bail!("Unexpected token {:?} at position {}", token, position);
+    }
+    Ok(expr)
+}
+
+impl Expr {
+    /// Evaluates the expression for an annotation. Comparisons on fields
+    /// that are not present are always `false`.
+    pub fn evaluate(&self, annotation: &Annotation) -> bool {
+        match self {
+            Expr::And(left, right) => left.evaluate(annotation) && right.evaluate(annotation),
+            Expr::Or(left, right) => left.evaluate(annotation) || right.evaluate(annotation),
+            Expr::Not(expr) => !expr.evaluate(annotation),
+            Expr::Has(field) => match field.value(annotation) {
+                None => false,
+                Some(value) => !value.is_empty(),
+            },
+            Expr::Compare(field, op, value) => {
+                let field_value = match field.value(annotation) {
+                    None => return false,
+                    Some(field_value) => field_value,
+                };
+                let ordering = match value {
+                    Value::Regex(regex) => {
+                        return regex.is_match(&field_value) == (*op == CmpOp::Match);
+                    }
+                    Value::Str(value) => field_value.as_str().cmp(value.as_str()),
+                    Value::Number(value) => match field_value.parse::<f64>() {
+                        Err(_) => return false,
+                        Ok(field_value) => match field_value.partial_cmp(value) {
+                            None => return false,
+                            Some(ordering) => ordering,
+                        },
+                    },
+                };
+                match op {
+                    CmpOp::Eq => ordering.is_eq(),
+                    CmpOp::Ne => ordering.is_ne(),
+                    CmpOp::Lt => ordering.is_lt(),
+                    CmpOp::Le => ordering.is_le(),
+                    CmpOp::Gt => ordering.is_gt(),
+                    CmpOp::Ge => ordering.is_ge(),
+                    CmpOp::Match | CmpOp::NotMatch => false,
+                }
+            }
+        }
+    }
+}
+
+pub fn filter_command(options: &FilterCommand) -> Result<()> {
+    let expr = parse_expression(&options.expression)?;
+    info!("Using filter: {:?}", expr);
+
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut count = 0;
+    let mut kept = 0;
+    for annotation in gff_file.annotations() {
+        count += 1;
+        if expr.evaluate(&annotation) == options.invert {
+            continue;
+        }
+        kept += 1;
+        // Writes to the output file
+        writer.write(&annotation)?;
+    }
+
+    info!("Kept {} out of {} annotations", kept, count);
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bio_rascal::gff::{Phase, Strand};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn annotation() -> Annotation {
+        let mut attributes = HashMap::new();
+        attributes.insert("product".to_string(), "serine kinase".to_string());
+        attributes.insert("score".to_string(), "5".to_string());
+        Annotation {
+            seq_id: "contig_1".into(),
+            source: "Prodigal".into(),
+            feature_type: "CDS".into(),
+            start: 10,
+            end: 360,
+            score: 50.5,
+            strand: Strand::from_value("+"),
+            phase: Phase::from_value("0").unwrap(),
+            uid: Uuid::new_v4(),
+            attributes,
+            taxon_id: 0,
+        }
+    }
+
+    fn evaluate(expression: &str) -> bool {
+        parse_expression(expression).unwrap().evaluate(&annotation())
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        // `and` before `or`
+        assert!(evaluate("start == 1 && end == 1 || feature_type == \"CDS\""));
+        assert!(evaluate("feature_type == \"CDS\" || start == 1 && end == 1"));
+        assert!(!evaluate("(feature_type == \"CDS\" || start == 1) && end == 1"));
+        assert!(evaluate("not (start == 1 or end == 1)"));
+        assert!(!evaluate("!start == 10 || end == 1"));
+    }
+
+    #[test]
+    fn quoted_strings() {
+        assert!(evaluate("product == \"serine kinase\""));
+        assert!(evaluate("product != 'a && b || (c)'"));
+        assert!(evaluate("product == 'serine kinase' and seq_id == \"contig_1\""));
+        assert_eq!(
+            tokenize(r#""a \"b\" == c""#).unwrap(),
+            vec![(0, Token::Str("a \"b\" == c".into()))]
+        );
+    }
+
+    #[test]
+    fn numeric_and_string_comparisons() {
+        assert!(evaluate("length >= 300"));
+        assert!(evaluate("score > 50 && score < 51"));
+        // numeric, not lexicographic
+        assert!(evaluate("start < 9.5e1"));
+        // lexicographic
+        assert!(evaluate("start < \"2\""));
+        assert!(evaluate("product =~ /kinase$/ && product !~ 'hypothetical'"));
+        assert!(!evaluate("product > 5"));
+    }
+
+    #[test]
+    fn attributes_prefix() {
+        assert!(evaluate("attributes.score == 5"));
+        assert!(evaluate("score == 50.5"));
+        assert!(evaluate("attributes.product =~ /serine/"));
+        assert!(!evaluate("has(attributes.start)"));
+        assert!(evaluate("has(start)"));
+    }
+
+    #[test]
+    fn missing_attributes() {
+        assert!(!evaluate("gene != \"abc\""));
+        assert!(!evaluate("gene == \"abc\""));
+        assert!(evaluate("!has(gene)"));
+        assert!(evaluate("!(gene == \"abc\")"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |expression: &str| parse_expression(expression).unwrap_err().to_string();
+        assert!(error("start == 'abc").contains("position 9"));
+        assert!(error("start = 1").contains("position 6"));
+        assert!(error("start == 1 & end == 2").contains("position 11"));
+        assert!(error("start 1").contains("position 6"));
+        assert!(error("(start == 1").contains("position 11"));
+        assert!(error("start == 1)").contains("position 10"));
+        assert!(error("start == 1 &&").contains("position 13"));
+        assert!(error("product =~ 5").contains("position 11"));
+        assert!(error("start == #").contains("position 9"));
+    }
+}
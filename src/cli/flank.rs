@@ -0,0 +1,128 @@
+use super::super::fasta::read_lengths;
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{file_or_stdin, file_or_stdout};
+use super::FlankCommand;
+use anyhow::{bail, Context, Result};
+use bio_rascal::gff::{Annotation, Phase};
+use bio_rascal::io::open_file;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Makes the annotation for a flanking region between `start` and `end`,
+/// which can extend before the start of the sequence (`start` < 1)
+fn flanking_region(
+    annotation: &Annotation,
+    feature_type: &str,
+    start: i64,
+    end: i64,
+    length: Option<u64>,
+    options: &FlankCommand,
+) -> Result<Option<Annotation>> {
+    let start = start.max(1) as u64;
+    let end = match length {
+        None => end as u64,
+        Some(length) => (end as u64).min(length),
+    };
+    if end < start {
+        return Ok(None);
+    }
+
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    attributes.insert(options.parent_attribute.clone(), annotation.uid.to_string());
+
+    Ok(Some(Annotation {
+        seq_id: annotation.seq_id.clone(),
+        source: annotation.source.clone(),
+        feature_type: feature_type.into(),
+        start,
+        end,
+        score: 0.,
+        strand: annotation.strand.clone(),
+        phase: Phase::from_value(".").context("Cannot parse Phase")?,
+        uid: Uuid::new_v4(),
+        attributes,
+        taxon_id: annotation.taxon_id,
+    }))
+}
+
+pub fn flank_command(options: &FlankCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    if options.upstream == 0 && options.downstream == 0 {
+        bail!("At least one of --upstream or --downstream is required");
+    }
+    if !options.feature_types.is_empty() {
+        info!("Using feature types: {}", options.feature_types.join(", "));
+    }
+
+    // the annotations are all read, since the directives can be anywhere
+    let gff_file = GffFile::from_reader(input_file);
+    let annotations: Vec<Annotation> = gff_file.annotations().collect();
+    info!("Read {} annotations", annotations.len());
+
+    let mut lengths = gff_file.sequence_regions();
+    if let Some(file_name) = &options.lengths {
+        info!("Reading sequence lengths from file {}", file_name.display());
+        lengths.extend(read_lengths(open_file(file_name)?).context("Cannot read the lengths")?);
+    }
+
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let upstream = options.upstream as i64;
+    let downstream = options.downstream as i64;
+    let mut no_length: HashSet<&str> = HashSet::new();
+    let mut count = 0;
+    for (position, annotation) in annotations.iter().enumerate() {
+        if !options.feature_types.is_empty()
+            && !options.feature_types.contains(&annotation.feature_type)
+        {
+            continue;
+        }
+        let length = lengths.get(&annotation.seq_id).copied();
+        if length.is_none() {
+            no_length.insert(&annotation.seq_id);
+        }
+
+        let start = annotation.start as i64;
+        let end = annotation.end as i64;
+        // annotations without a strand are considered on the forward one
+        let (five_prime, three_prime) = match annotation.strand.to_string().as_str() {
+            "-" => ((end + 1, end + upstream), (start - downstream, start - 1)),
+            _ => ((start - upstream, start - 1), (end + 1, end + downstream)),
+        };
+
+        let mut regions = Vec::new();
+        if upstream > 0 {
+            regions.push(("five_prime_flanking_region", five_prime));
+        }
+        if downstream > 0 {
+            regions.push(("three_prime_flanking_region", three_prime));
+        }
+        for (feature_type, (region_start, region_end)) in regions {
+            if let Some(region) = flanking_region(
+                annotation,
+                feature_type,
+                region_start,
+                region_end,
+                length,
+                options,
+            )? {
+                writer.write_at(&region, position)?;
+                count += 1;
+            }
+        }
+    }
+
+    info!("Written {} flanking region(s)", count);
+    if !no_length.is_empty() {
+        warn!(
+            "{} sequence(s) without a length, the regions at their end are not clipped",
+            no_length.len()
+        );
+    }
+
+    writer.finish()
+}
@@ -0,0 +1,78 @@
+use super::super::fasta::{read_fasta, reverse_complement, subsequence, write_fasta, Sequences};
+use super::super::gff::GffFile;
+use super::super::utils::{file_or_stdin, file_or_stdout, is_minus_strand, join_field_values};
+use super::GetseqCommand;
+use anyhow::Result;
+use bio_rascal::gff::Annotation;
+use bio_rascal::io::open_file_base;
+use log::{info, warn};
+use std::io::{BufReader, Write};
+
+/// Writes the sequence of an annotation, returns false if the sequence
+/// was not found
+fn write_annotation_sequence(
+    output_file: &mut dyn Write,
+    sequences: &Sequences,
+    annotation: &Annotation,
+    options: &GetseqCommand,
+) -> Result<bool> {
+    let sequence = match sequences.get(&annotation.seq_id) {
+        None => return Ok(false),
+        Some(sequence) => sequence,
+    };
+    let sequence = subsequence(
+        sequence,
+        annotation.start as usize,
+        annotation.end as usize,
+        options.flank,
+    );
+    let header = join_field_values(annotation, &options.attributes, " ");
+    if is_minus_strand(annotation) {
+        write_fasta(output_file, &header, &reverse_complement(sequence), options.width)?;
+    } else {
+        write_fasta(output_file, &header, sequence, options.width)?;
+    }
+    Ok(true)
+}
+
+pub fn getseq_command(options: &GetseqCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let mut output_file = file_or_stdout(&options.output_file)?;
+
+    let gff_file = GffFile::from_reader(input_file);
+
+    let mut count = 0;
+    let mut missing = 0;
+    match &options.fasta_file {
+        Some(fasta_file) => {
+            info!("Reading sequences from file {}", fasta_file.display());
+            let sequences = read_fasta(BufReader::new(open_file_base(fasta_file)?))?;
+            for annotation in gff_file.annotations() {
+                count += 1;
+                if !write_annotation_sequence(&mut output_file, &sequences, &annotation, options)? {
+                    missing += 1;
+                }
+            }
+        }
+        None => {
+            // the sequences are after the annotations
+            info!("Using sequences in the ##FASTA section");
+            let annotations: Vec<Annotation> = gff_file.annotations().collect();
+            let sequences = gff_file.read_sequences()?;
+            for annotation in annotations {
+                count += 1;
+                if !write_annotation_sequence(&mut output_file, &sequences, &annotation, options)? {
+                    missing += 1;
+                }
+            }
+        }
+    }
+
+    if missing > 0 {
+        warn!("Sequence not found for {} annotation(s)", missing);
+    }
+    info!("Written {} sequences", count - missing);
+
+    Ok(())
+}
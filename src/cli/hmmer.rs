@@ -0,0 +1,277 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{annotation_key, file_or_stdin, file_or_stdout};
+use super::{HmmerCommand, HmmerFormat};
+use anyhow::{bail, Context, Result};
+use bio_rascal::io::open_file;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::Path;
+
+/// Score compared to the threshold of a model, as in the KOfam `ko_list`
+#[derive(Clone, Copy, PartialEq)]
+enum ScoreType {
+    /// Score of the full sequence
+    Full,
+    /// Score of the best domain
+    Domain,
+}
+
+/// Threshold and score type for each model
+type Thresholds = HashMap<String, (f64, ScoreType)>;
+
+/// A model assigned to a gene
+struct HmmerHit {
+    model: String,
+    score: f64,
+    /// E-value as written in the file, to keep the notation
+    evalue: String,
+}
+
+/// Position of the columns in a line of the HMMER table
+struct Columns {
+    target: usize,
+    target_accession: usize,
+    query: usize,
+    query_accession: usize,
+    full_evalue: usize,
+    full_score: usize,
+    domain_evalue: usize,
+    domain_score: usize,
+    /// Number of columns before the description
+    size: usize,
+}
+
+impl Columns {
+    fn from_format(format: HmmerFormat) -> Self {
+        match format {
+            HmmerFormat::Tblout => Columns {
+                target: 0,
+                target_accession: 1,
+                query: 2,
+                query_accession: 3,
+                full_evalue: 4,
+                full_score: 5,
+                domain_evalue: 7,
+                domain_score: 8,
+                size: 18,
+            },
+            HmmerFormat::Domtblout => Columns {
+                target: 0,
+                target_accession: 1,
+                query: 3,
+                query_accession: 4,
+                full_evalue: 6,
+                full_score: 7,
+                domain_evalue: 12,
+                domain_score: 13,
+                size: 22,
+            },
+        }
+    }
+}
+
+/// Reads a file with the thresholds for each model, tab separated, with the
+/// model name, threshold and, optionally, the score type (`full` or
+/// `domain`). The KOfam `ko_list` file uses this format: its header and
+/// models without a threshold (`-`) are skipped.
+fn read_thresholds<P: AsRef<Path>>(file_name: P) -> Result<Thresholds> {
+    let file_handle = open_file(file_name.as_ref())?;
+
+    let mut thresholds = Thresholds::new();
+    for line in file_handle.lines() {
+        let line = line?;
+        if line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 2 {
+            continue;
+        }
+        let threshold: f64 = match fields[1].trim().parse() {
+            Err(_) => continue,
+            Ok(threshold) => threshold,
+        };
+        let score_type = match fields.get(2).map(|value| value.trim()) {
+            Some("domain") => ScoreType::Domain,
+            _ => ScoreType::Full,
+        };
+        thresholds.insert(fields[0].trim().to_string(), (threshold, score_type));
+    }
+
+    info!("Read thresholds for {} model(s)", thresholds.len());
+
+    Ok(thresholds)
+}
+
+/// Reads a HMMER table (space separated), keeping the models passing the
+/// thresholds for each gene, sorted by score, highest first
+fn read_hmmer(
+    options: &HmmerCommand,
+    thresholds: &Thresholds,
+) -> Result<HashMap<String, Vec<HmmerHit>>> {
+    let file_handle = open_file(&options.hmmer_file)?;
+    let columns = Columns::from_format(options.format);
+
+    let mut hits: HashMap<String, Vec<HmmerHit>> = HashMap::new();
+    let mut count = 0;
+    for (index, line) in file_handle.lines().enumerate() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        // the description, after the last column, can contain spaces
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < columns.size {
+            bail!(
+                "Line {} has {} columns, expected at least {}",
+                index + 1,
+                fields.len(),
+                columns.size
+            );
+        }
+        count += 1;
+
+        let (gene, model, model_accession) = if options.hmmscan {
+            (
+                fields[columns.query],
+                fields[columns.target],
+                fields[columns.target_accession],
+            )
+        } else {
+            (
+                fields[columns.target],
+                fields[columns.query],
+                fields[columns.query_accession],
+            )
+        };
+        let model = match options.use_accession && model_accession != "-" {
+            true => model_accession,
+            false => model,
+        };
+        let (threshold, score_type) = thresholds
+            .get(model)
+            .copied()
+            .unwrap_or((options.min_score, ScoreType::Full));
+        let (score, evalue) = match score_type {
+            ScoreType::Full => (columns.full_score, columns.full_evalue),
+            ScoreType::Domain => (columns.domain_score, columns.domain_evalue),
+        };
+        let score: f64 = fields[score]
+            .parse()
+            .with_context(|| format!("Cannot parse the score at line {}", index + 1))?;
+        if score < threshold {
+            continue;
+        }
+
+        let gene_hits = hits.entry(gene.to_string()).or_default();
+        // domtblout has a line for each domain
+        match gene_hits.iter_mut().find(|hit| hit.model == model) {
+            Some(hit) => {
+                if score > hit.score {
+                    hit.score = score;
+                    hit.evalue = fields[evalue].to_string();
+                }
+            }
+            None => gene_hits.push(HmmerHit {
+                model: model.to_string(),
+                score,
+                evalue: fields[evalue].to_string(),
+            }),
+        }
+    }
+
+    for gene_hits in hits.values_mut() {
+        gene_hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    }
+
+    info!(
+        "Read {} line(s), with {} gene(s) assigned to a model",
+        count,
+        hits.len()
+    );
+
+    Ok(hits)
+}
+
+pub fn hmmer_command(options: &HmmerCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    let thresholds = match &options.thresholds_file {
+        None => Thresholds::new(),
+        Some(file_name) => {
+            info!("Reading thresholds from file {}", file_name.display());
+            read_thresholds(file_name).context("Cannot read the thresholds file")?
+        }
+    };
+    info!(
+        "Reading HMMER hits from file {}",
+        options.hmmer_file.display()
+    );
+    let hits = read_hmmer(options, &thresholds).context("Cannot read the HMMER file")?;
+
+    if options.prodigal_gene {
+        info!("Using key from Prodigal sequences")
+    } else {
+        info!("Using '{}' as key", options.key);
+    }
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut matched: HashSet<String> = HashSet::new();
+    let mut count = 0;
+    let mut edited = 0;
+    for mut annotation in gff_file.annotations() {
+        count += 1;
+        let key_value = annotation_key(&annotation, &options.key, options.prodigal_gene);
+        match key_value.and_then(|key_value| hits.get_key_value(&key_value)) {
+            Some((gene, gene_hits)) => {
+                let gene_hits = match options.all {
+                    true => &gene_hits[..],
+                    false => &gene_hits[..1],
+                };
+                let join = |value: fn(&HmmerHit) -> String| {
+                    gene_hits
+                        .iter()
+                        .map(value)
+                        .collect::<Vec<String>>()
+                        .join(",")
+                };
+                annotation
+                    .attributes
+                    .insert(options.attribute.clone(), join(|hit| hit.model.clone()));
+                if let Some(attribute) = &options.score_attribute {
+                    annotation
+                        .attributes
+                        .insert(attribute.clone(), join(|hit| hit.score.to_string()));
+                }
+                if let Some(attribute) = &options.evalue_attribute {
+                    annotation
+                        .attributes
+                        .insert(attribute.clone(), join(|hit| hit.evalue.clone()));
+                }
+                matched.insert(gene.clone());
+                edited += 1;
+            }
+            None => {
+                if options.only_edited {
+                    continue;
+                }
+            }
+        }
+        writer.write(&annotation)?;
+    }
+
+    info!("Added models to {} of {} annotation(s)", edited, count);
+    if matched.len() < hits.len() {
+        warn!(
+            "{} gene(s) with models not found in the GFF",
+            hits.len() - matched.len()
+        );
+    }
+
+    writer.finish()
+}
@@ -0,0 +1,215 @@
+use super::super::bed::{BedFields, BedReader};
+use super::super::utils::{file_or_stdin, file_or_stdout};
+use super::{ImportCommand, ImportFormat};
+use anyhow::{bail, Context, Result};
+use bio_rascal::gff::{Annotation, Phase, Strand};
+use log::{info, warn};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Builds an annotation from a list of field names and values, using the
+/// same names as `view`. `seq_id`, `start` and `end` are required, the other
+/// built-in fields have default values (`source` and `feature_type` from
+/// the options) and a new `uid` is generated if missing. Fields that are
+/// not built-in are added as attributes, `length` is ignored. Empty values
+/// are considered missing.
+fn annotation_from_fields<I: IntoIterator<Item = (String, String)>>(
+    fields: I,
+    options: &ImportCommand,
+) -> Result<Annotation> {
+    let mut values: HashMap<String, String> = HashMap::new();
+    for (key, value) in fields {
+        if !value.is_empty() {
+            values.insert(key, value);
+        }
+    }
+
+    let mut take = |key: &str| values.remove(key);
+
+    let seq_id = take("seq_id").context("Missing seq_id")?;
+    let start = take("start")
+        .context("Missing start")?
+        .parse()
+        .context("Parsing Start field failed")?;
+    let end = take("end")
+        .context("Missing end")?
+        .parse()
+        .context("Parsing End field failed")?;
+    let source = take("source")
+        .or_else(|| options.source.clone())
+        .unwrap_or_else(|| ".".into());
+    let feature_type = take("feature_type")
+        .or_else(|| options.feature_type.clone())
+        .unwrap_or_else(|| ".".into());
+    let score = match take("score") {
+        None => 0.,
+        Some(value) => value.parse().unwrap_or(0.),
+    };
+    let strand = Strand::from_value(&take("strand").unwrap_or_else(|| ".".into()));
+    let phase = Phase::from_value(&take("phase").unwrap_or_else(|| ".".into()))
+        .context("Cannot parse Phase")?;
+    let uid = match take("uid") {
+        None => Uuid::new_v4(),
+        Some(value) => Uuid::from_str(&value).context("Cannot convert Uuid")?,
+    };
+    let taxon_id = match take("taxon_id") {
+        None => 0,
+        Some(value) => value.parse().context("Cannot convert taxon_id to a number")?,
+    };
+    take("length");
+
+    Ok(Annotation {
+        seq_id,
+        source,
+        feature_type,
+        start,
+        end,
+        score,
+        strand,
+        phase,
+        uid,
+        attributes: values,
+        taxon_id,
+    })
+}
+
+/// Converts a JSON value to the string used in the annotation, `null`
+/// becomes an empty string (missing)
+fn json_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// Builds an annotation from a JSON object. An `attributes` object, as
+/// written by the `json` command, is merged with the other fields.
+fn annotation_from_json(value: &Value, options: &ImportCommand) -> Result<Annotation> {
+    let object = match value {
+        Value::Object(object) => object,
+        _ => bail!("Expected a JSON object, found: {}", value),
+    };
+    let mut fields: Vec<(String, String)> = Vec::with_capacity(object.len());
+    for (key, value) in object {
+        match (key.as_str(), value) {
+            ("attributes", Value::Object(attributes)) => fields.extend(
+                attributes
+                    .iter()
+                    .map(|(key, value)| (key.clone(), json_to_string(value))),
+            ),
+            _ => fields.push((key.clone(), json_to_string(value))),
+        }
+    }
+    annotation_from_fields(fields, options)
+}
+
+pub fn import_command(options: &ImportCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let mut output_file = file_or_stdout(&options.output_file)?;
+
+    let mut count = 0;
+    match options.format {
+        ImportFormat::Bed => {
+            info!("Reading BED input");
+            let fields = BedFields {
+                source: options.source.clone().unwrap_or_else(|| ".".into()),
+                feature_type: options
+                    .feature_type
+                    .clone()
+                    .unwrap_or_else(|| "region".into()),
+                name_attribute: options.name_attribute.clone(),
+                columns: options.columns.clone(),
+            };
+            for annotation in BedReader::from_reader(input_file, fields) {
+                writeln!(output_file, "{}", annotation?)?;
+                count += 1;
+            }
+        }
+        ImportFormat::Json => {
+            info!("Reading JSON input");
+            let mut lines = BufReader::new(input_file)
+                .lines()
+                .enumerate()
+                .peekable();
+            // a JSON array, like the default output of `json`
+            let is_array = match lines.peek() {
+                Some((_, Ok(line))) => line.trim_start().starts_with('['),
+                _ => false,
+            };
+            let values: Box<dyn Iterator<Item = Result<(usize, Value)>>> = if is_array {
+                let text = lines
+                    .map(|(_, line)| line)
+                    .collect::<std::io::Result<Vec<String>>>()?
+                    .join("\n");
+                let values: Vec<Value> =
+                    serde_json::from_str(&text).context("Cannot parse JSON array")?;
+                Box::new(values.into_iter().enumerate().map(Ok))
+            } else {
+                Box::new(
+                    lines
+                        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+                        .map(|(index, line)| {
+                            let value = serde_json::from_str(&line?).with_context(|| {
+                                format!("Cannot parse JSON at line {}", index + 1)
+                            })?;
+                            Ok((index, value))
+                        }),
+                )
+            };
+            for value in values {
+                let (index, value) = value?;
+                let annotation = annotation_from_json(&value, options)
+                    .with_context(|| format!("Invalid annotation in record {}", index + 1))?;
+                writeln!(output_file, "{}", annotation)?;
+                count += 1;
+            }
+        }
+        ImportFormat::Tsv => {
+            info!("Reading tab separated input");
+            let mut lines = BufReader::new(input_file).lines();
+            let header: Vec<String> = match lines.next() {
+                None => bail!("The input is empty, a header is required"),
+                Some(line) => line?
+                    .trim_start_matches('#')
+                    .split('\t')
+                    .map(|field| field.trim().to_string())
+                    .collect(),
+            };
+            info!("Using columns: {}", header.join(", "));
+            for (index, line) in lines.enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let values: Vec<&str> = line.split('\t').collect();
+                if values.len() != header.len() {
+                    warn!(
+                        "Line {} has {} columns, expected {}",
+                        index + 2,
+                        values.len(),
+                        header.len()
+                    );
+                }
+                let annotation = annotation_from_fields(
+                    header
+                        .iter()
+                        .cloned()
+                        .zip(values.iter().map(|value| value.to_string())),
+                    options,
+                )
+                .with_context(|| format!("Invalid annotation at line {}", index + 2))?;
+                writeln!(output_file, "{}", annotation)?;
+                count += 1;
+            }
+        }
+    }
+
+    info!("Imported {} annotations", count);
+
+    Ok(())
+}
@@ -0,0 +1,390 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{
+    annotation_key, encode_attribute_value, file_or_stdin, file_or_stdout, is_minus_strand,
+};
+use super::InterproCommand;
+use anyhow::{bail, Context, Result};
+use bio_rascal::gff::{Annotation, Phase};
+use bio_rascal::io::open_file;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::Path;
+use uuid::Uuid;
+
+/// A line of the InterProScan TSV output
+struct Hit {
+    analysis: String,
+    signature: String,
+    description: Option<String>,
+    /// Positions on the protein, 1-based
+    start: u64,
+    end: u64,
+    score: f64,
+    interpro: Option<String>,
+    go_terms: Vec<String>,
+}
+
+/// Hits for each protein, in the order found in the file
+type HitTable = HashMap<String, Vec<Hit>>;
+
+/// Returns `None` for the missing values of InterProScan (`-` or empty)
+fn optional_value(value: Option<&&str>) -> Option<String> {
+    match value.map(|value| value.trim()) {
+        None | Some("") | Some("-") => None,
+        Some(value) => Some(value.to_string()),
+    }
+}
+
+/// Parses a line of the InterProScan TSV output, with 11 to 15 columns
+fn parse_hit(line: &str) -> Result<(String, Hit)> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 11 {
+        bail!("Expected at least 11 columns, found {}", fields.len());
+    }
+    // GO terms may include the source, like `GO:0005524(InterPro)`
+    let go_terms = match optional_value(fields.get(13)) {
+        None => Vec::new(),
+        Some(value) => value
+            .split('|')
+            .map(|term| term.split('(').next().unwrap_or_default().to_string())
+            .collect(),
+    };
+    let hit = Hit {
+        analysis: fields[3].to_string(),
+        signature: fields[4].to_string(),
+        description: optional_value(fields.get(5)),
+        start: fields[6]
+            .parse()
+            .context("Cannot parse the start position")?,
+        end: fields[7].parse().context("Cannot parse the end position")?,
+        score: fields[8].parse().unwrap_or(0.),
+        interpro: optional_value(fields.get(11)),
+        go_terms,
+    };
+    Ok((fields[0].to_string(), hit))
+}
+
+/// Reads the InterProScan TSV output, keeping all the hits for each
+/// protein
+fn read_interpro<P: AsRef<Path>>(file_name: P) -> Result<HitTable> {
+    let file_handle = open_file(file_name.as_ref())?;
+
+    let mut table = HitTable::new();
+    let mut count = 0;
+    for (index, line) in file_handle.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (protein, hit) =
+            parse_hit(&line).with_context(|| format!("Invalid hit at line {}", index + 1))?;
+        table.entry(protein).or_default().push(hit);
+        count += 1;
+    }
+
+    info!("Read {} hit(s) for {} protein(s)", count, table.len());
+
+    Ok(table)
+}
+
+/// Adds the values to the attribute, removing duplicates and keeping the
+/// order in which they are found
+fn set_multi_value<'a, I: Iterator<Item = &'a str>>(
+    annotation: &mut Annotation,
+    attribute: &str,
+    values: I,
+) {
+    let mut seen = HashSet::new();
+    let values: Vec<&str> = values.filter(|value| seen.insert(*value)).collect();
+    if !values.is_empty() {
+        annotation
+            .attributes
+            .insert(attribute.to_string(), values.join(","));
+    }
+}
+
+/// Key grouping the parts of a feature split in multiple lines, like a CDS
+/// with introns: parts share the sequence, feature type and `ID` (or
+/// `Parent`, if they have no `ID`)
+type PartKey = (String, String, String);
+
+fn part_key(annotation: &Annotation) -> Option<PartKey> {
+    annotation
+        .get_attr("ID")
+        .or_else(|| annotation.get_attr("Parent"))
+        .map(|id| {
+            (
+                annotation.seq_id.clone(),
+                annotation.feature_type.clone(),
+                id,
+            )
+        })
+}
+
+/// Builds the `protein_match` annotations for a hit, with the protein
+/// positions projected onto the coordinates of the CDS parts, which must
+/// be in transcription order (descending positions on the minus strand).
+/// The phase of the first part is used and one annotation is returned for
+/// each part overlapped by the hit, all with the same `ID`. The positions
+/// are clipped to the CDS.
+fn hit_to_annotations(hit: &Hit, parts: &[&Annotation]) -> Result<Vec<Annotation>> {
+    let first = match parts.first() {
+        Some(first) => first,
+        None => return Ok(Vec::new()),
+    };
+    let phase = first.phase.to_string().parse::<u64>().unwrap_or(0);
+    // positions on the concatenated parts, 0-based and inclusive
+    let hit_start = phase + (hit.start - 1) * 3;
+    let hit_end = phase + hit.end * 3 - 1;
+
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    let parent = match first.get_attr("ID") {
+        Some(id) => id,
+        None => first.uid.to_string(),
+    };
+    attributes.insert("ID".into(), Uuid::new_v4().to_string());
+    attributes.insert("Parent".into(), parent);
+    attributes.insert("Name".into(), encode_attribute_value(&hit.signature));
+    if let Some(description) = &hit.description {
+        attributes.insert("signature_desc".into(), encode_attribute_value(description));
+    }
+    if let Some(interpro) = &hit.interpro {
+        attributes.insert("Dbxref".into(), format!("InterPro:{}", interpro));
+    }
+    if !hit.go_terms.is_empty() {
+        attributes.insert("Ontology_term".into(), hit.go_terms.join(","));
+    }
+
+    let mut annotations = Vec::new();
+    let mut offset = 0;
+    for part in parts {
+        let part_offset = offset;
+        offset += part.length();
+        let overlap_start = hit_start.max(part_offset) - part_offset;
+        let overlap_end = match hit_end.min(offset - 1).checked_sub(part_offset) {
+            Some(overlap_end) if overlap_end >= overlap_start => overlap_end,
+            _ => continue,
+        };
+        let (start, end) = if is_minus_strand(part) {
+            (part.end - overlap_end, part.end - overlap_start)
+        } else {
+            (part.start + overlap_start, part.start + overlap_end)
+        };
+        annotations.push(Annotation {
+            seq_id: part.seq_id.clone(),
+            source: hit.analysis.clone(),
+            feature_type: "protein_match".into(),
+            start,
+            end,
+            score: hit.score,
+            strand: part.strand.clone(),
+            phase: Phase::from_value(".").context("Cannot parse Phase")?,
+            uid: Uuid::new_v4(),
+            attributes: attributes.clone(),
+            taxon_id: part.taxon_id,
+        });
+    }
+
+    Ok(annotations)
+}
+
+pub fn interpro_command(options: &InterproCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    info!(
+        "Reading InterProScan hits from file {}",
+        options.interpro_file.display()
+    );
+    let table =
+        read_interpro(&options.interpro_file).context("Cannot read the InterProScan file")?;
+
+    if options.prodigal_gene {
+        info!("Using key from Prodigal sequences")
+    } else {
+        info!("Using '{}' as key", options.key);
+    }
+    info!("Using analyses: {}", options.analyses.join(", "));
+
+    // the annotations are all read, to find the parts of each CDS
+    let gff_file = GffFile::from_reader(input_file);
+    let annotations: Vec<Annotation> = gff_file.annotations().collect();
+    let mut parts: HashMap<PartKey, Vec<&Annotation>> = HashMap::new();
+    for annotation in &annotations {
+        if let Some(part_key) = part_key(annotation) {
+            parts.entry(part_key).or_default().push(annotation);
+        }
+    }
+    for group in parts.values_mut() {
+        if group.first().is_some_and(|part| is_minus_strand(part)) {
+            group.sort_by_key(|part| std::cmp::Reverse(part.end));
+        } else {
+            group.sort_by_key(|part| part.start);
+        }
+    }
+
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut matched: HashSet<String> = HashSet::new();
+    let mut projected: HashSet<PartKey> = HashSet::new();
+    let mut edited = 0;
+    let mut matches = 0;
+    for (position, annotation) in annotations.iter().enumerate() {
+        let key_value = annotation_key(annotation, &options.key, options.prodigal_gene);
+        let (protein, hits) = match key_value.and_then(|key_value| table.get_key_value(&key_value))
+        {
+            Some(value) => value,
+            None => {
+                if !options.only_edited {
+                    writer.write_at(annotation, position)?;
+                }
+                continue;
+            }
+        };
+        matched.insert(protein.clone());
+        edited += 1;
+        let mut annotation = annotation.clone();
+
+        // InterPro entries and GO terms are taken from all the hits
+        set_multi_value(
+            &mut annotation,
+            "InterPro",
+            hits.iter().filter_map(|hit| hit.interpro.as_deref()),
+        );
+        set_multi_value(
+            &mut annotation,
+            "Ontology_term",
+            hits.iter()
+                .flat_map(|hit| hit.go_terms.iter().map(|term| term.as_str())),
+        );
+        let hits: Vec<&Hit> = hits
+            .iter()
+            .filter(|hit| options.analyses.contains(&hit.analysis))
+            .collect();
+        for analysis in &options.analyses {
+            set_multi_value(
+                &mut annotation,
+                analysis,
+                hits.iter()
+                    .filter(|hit| &hit.analysis == analysis)
+                    .map(|hit| hit.signature.as_str()),
+            );
+        }
+        writer.write_at(&annotation, position)?;
+
+        if !options.protein_match {
+            continue;
+        }
+        // the hits are projected once for all the parts of a CDS
+        let part_key = part_key(&annotation);
+        let cds_parts = match &part_key {
+            Some(part_key) if !projected.insert(part_key.clone()) => continue,
+            Some(part_key) => parts[part_key].clone(),
+            None => vec![&annotations[position]],
+        };
+        for hit in hits {
+            for protein_match in hit_to_annotations(hit, &cds_parts)? {
+                writer.write_at(&protein_match, position)?;
+                matches += 1;
+            }
+        }
+    }
+
+    info!(
+        "Added attributes to {} annotation(s), using hits of {} of {} protein(s)",
+        edited,
+        matched.len(),
+        table.len()
+    );
+    if options.protein_match {
+        info!("Written {} protein_match feature(s)", matches);
+    }
+    if matched.len() < table.len() {
+        warn!(
+            "{} protein(s) with hits not found in the GFF",
+            table.len() - matched.len()
+        );
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::utils::decode_attribute_value;
+    use super::*;
+    use bio_rascal::gff::Strand;
+
+    fn cds_part(start: u64, end: u64, phase: &str) -> Annotation {
+        let mut attributes = HashMap::new();
+        attributes.insert("ID".to_string(), "cds1".to_string());
+        Annotation {
+            seq_id: "seq1".into(),
+            source: ".".into(),
+            feature_type: "CDS".into(),
+            start,
+            end,
+            score: 0.,
+            strand: Strand::from_value("-"),
+            phase: Phase::from_value(phase).unwrap(),
+            uid: Uuid::new_v4(),
+            attributes,
+            taxon_id: 0,
+        }
+    }
+
+    fn hit(start: u64, end: u64) -> Hit {
+        Hit {
+            analysis: "Pfam".into(),
+            signature: "PF00001".into(),
+            description: None,
+            start,
+            end,
+            score: 0.,
+            interpro: None,
+            go_terms: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn minus_strand_two_exons() {
+        // transcription order on the minus strand: 201..240 first
+        let first = cds_part(201, 240, "0");
+        let second = cds_part(101, 150, "2");
+        let parts = vec![&first, &second];
+
+        let positions = |hit: &Hit| -> Vec<(u64, u64)> {
+            hit_to_annotations(hit, &parts)
+                .unwrap()
+                .iter()
+                .map(|annotation| (annotation.start, annotation.end))
+                .collect()
+        };
+        assert_eq!(positions(&hit(1, 5)), vec![(226, 240)]);
+        assert_eq!(positions(&hit(10, 20)), vec![(201, 213), (131, 150)]);
+        assert_eq!(positions(&hit(25, 30)), vec![(101, 118)]);
+        // clipped to the end of the CDS
+        assert_eq!(positions(&hit(28, 40)), vec![(101, 109)]);
+
+        let annotations = hit_to_annotations(&hit(10, 20), &parts).unwrap();
+        assert_eq!(annotations[0].get_attr("ID"), annotations[1].get_attr("ID"));
+        assert_eq!(annotations[0].get_attr("Parent"), Some("cds1".to_string()));
+    }
+
+    #[test]
+    fn descriptions_are_encoded() {
+        let part = cds_part(101, 400, "0");
+        let mut hit = hit(1, 10);
+        hit.description = Some("Kinase; N-terminal, ATP=binding".into());
+
+        let annotations = hit_to_annotations(&hit, &[&part]).unwrap();
+        let description = annotations[0].get_attr("signature_desc").unwrap();
+        assert_eq!(description, "Kinase%3B N-terminal%2C ATP%3Dbinding");
+        assert_eq!(
+            decode_attribute_value(&description),
+            "Kinase; N-terminal, ATP=binding"
+        );
+    }
+}
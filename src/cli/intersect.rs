@@ -0,0 +1,135 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::intervals::{overlap_length, IntervalIndex};
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
+use super::IntersectCommand;
+use anyhow::Result;
+use bio_rascal::gff::{Annotation, GffReader};
+use itertools::Itertools;
+use log::info;
+use std::collections::HashSet;
+
+/// Returns the features overlapping the annotation, with the options
+/// passed (strand and minimum overlap), and the length of the overlap
+fn find_overlaps<'a>(
+    index: &'a IntervalIndex,
+    annotation: &Annotation,
+    options: &IntersectCommand,
+) -> Vec<(&'a Annotation, u64)> {
+    let length = (annotation.end - annotation.start + 1) as f64;
+    index
+        .overlapping(&annotation.seq_id, annotation.start, annotation.end)
+        .into_iter()
+        .filter(|feature| {
+            !options.same_strand || feature.strand.to_string() == annotation.strand.to_string()
+        })
+        .map(|feature| (feature, overlap_length(annotation, feature)))
+        .filter(|(_, overlap)| *overlap as f64 / length >= options.min_overlap)
+        .collect()
+}
+
+/// Splits the `field:attribute` values of `--copy-attributes`, the
+/// attribute name is the same as the field if not passed
+fn copy_pairs(copy_attributes: &[String]) -> Vec<(&str, &str)> {
+    copy_attributes
+        .iter()
+        .map(|value| value.split_once(':').unwrap_or((value, value)))
+        .collect()
+}
+
+/// Writes a table with a line for each pair of overlapping annotation and
+/// feature
+fn write_pairs(index: &IntervalIndex, options: &IntersectCommand) -> Result<()> {
+    let input_file = file_or_stdin(&options.input_file)?;
+    let mut output_file = file_or_stdout(&options.output_file)?;
+
+    if options.header {
+        writeln!(
+            output_file,
+            "#{}\t{}\toverlap",
+            options.fields.iter().join("\t"),
+            options
+                .fields
+                .iter()
+                .map(|field| format!("other_{}", field))
+                .join("\t")
+        )?;
+    }
+
+    let mut count = 0;
+    for annotation in GffReader::from_reader(input_file) {
+        for (feature, overlap) in find_overlaps(index, &annotation, options) {
+            let values = options
+                .fields
+                .iter()
+                .map(|field| get_field_value(&annotation, field).unwrap_or_default())
+                .chain(
+                    options
+                        .fields
+                        .iter()
+                        .map(|field| get_field_value(feature, field).unwrap_or_default()),
+                )
+                .join("\t");
+            writeln!(output_file, "{}\t{}", values, overlap)?;
+            count += 1;
+        }
+    }
+
+    info!("Written {} overlapping pair(s)", count);
+
+    Ok(())
+}
+
+pub fn intersect_command(options: &IntersectCommand) -> Result<()> {
+    let index = IntervalIndex::from_file(&options.other_file, options.bed)?;
+
+    if options.pairs {
+        return write_pairs(&index, options);
+    }
+
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    let copy_attributes = copy_pairs(&options.copy_attributes);
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut count = 0;
+    let mut overlapping = 0;
+    let mut written = 0;
+    for mut annotation in gff_file.annotations() {
+        count += 1;
+        let overlaps = find_overlaps(&index, &annotation, options);
+        if !overlaps.is_empty() {
+            overlapping += 1;
+        }
+        if !options.keep_all && overlaps.is_empty() != options.invert {
+            continue;
+        }
+
+        for (field, attribute) in &copy_attributes {
+            let mut seen = HashSet::new();
+            let values: Vec<String> = overlaps
+                .iter()
+                .filter_map(|(feature, _)| get_field_value(feature, field))
+                .filter(|value| !value.is_empty() && seen.insert(value.clone()))
+                .collect();
+            if !values.is_empty() {
+                annotation
+                    .attributes
+                    .insert(attribute.to_string(), values.join(","));
+            }
+        }
+
+        writer.write(&annotation)?;
+        written += 1;
+    }
+
+    info!(
+        "{} of {} annotation(s) overlap, {} written",
+        overlapping, count, written
+    );
+
+    writer.finish()
+}
@@ -0,0 +1,113 @@
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
+use super::JsonCommand;
+use anyhow::Result;
+use bio_rascal::gff::{Annotation, GffReader};
+use itertools::Itertools;
+use log::info;
+use serde_json::{Map, Value};
+
+/// Built-in fields, in the order used when all fields are written
+const BUILTIN_FIELDS: [&str; 10] = [
+    "seq_id",
+    "source",
+    "feature_type",
+    "start",
+    "end",
+    "score",
+    "strand",
+    "phase",
+    "uid",
+    "taxon_id",
+];
+
+/// Converts the value of a field to a JSON value. If `numbers` is true,
+/// the numeric fields are converted to JSON numbers, if possible.
+fn field_to_json(field: &str, value: Option<String>, numbers: bool) -> Value {
+    let value = match value {
+        None => return Value::Null,
+        Some(value) => value,
+    };
+    if !numbers {
+        return Value::String(value);
+    }
+    match field {
+        "start" | "end" | "length" | "taxon_id" | "phase" => match value.parse::<u64>() {
+            Ok(number) => Value::from(number),
+            Err(_) => Value::Null,
+        },
+        "score" => match value.parse::<f64>() {
+            Ok(number) => Value::from(number),
+            Err(_) => Value::Null,
+        },
+        _ => Value::String(value),
+    }
+}
+
+/// Makes a JSON object from an annotation. If `fields` is empty, all
+/// built-in fields are used and the attributes are included as an object
+/// under the `attributes` key, otherwise only the requested fields are used.
+fn annotation_to_json(annotation: &Annotation, fields: &[String], numbers: bool) -> Value {
+    let mut object = Map::new();
+
+    if fields.is_empty() {
+        for field in BUILTIN_FIELDS {
+            let value = get_field_value(annotation, field);
+            object.insert(field.to_string(), field_to_json(field, value, numbers));
+        }
+        let attributes: Map<String, Value> = annotation
+            .attributes
+            .iter()
+            .sorted_by(|a, b| a.0.cmp(b.0))
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect();
+        object.insert("attributes".into(), Value::Object(attributes));
+    } else {
+        for field in fields {
+            let value = get_field_value(annotation, field);
+            object.insert(field.clone(), field_to_json(field, value, numbers));
+        }
+    }
+
+    Value::Object(object)
+}
+
+pub fn json_command(options: &JsonCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let mut output_file = file_or_stdout(&options.output_file)?;
+
+    let reader = GffReader::from_reader(input_file);
+
+    if options.attributes.is_empty() {
+        info!("All fields will be written");
+    } else {
+        info!("{} attributes will be written", options.attributes.len());
+    }
+
+    if !options.lines {
+        write!(output_file, "[")?;
+    }
+
+    let mut count = 0;
+    for annotation in reader {
+        let value = annotation_to_json(&annotation, &options.attributes, options.numbers);
+        if options.lines {
+            writeln!(output_file, "{}", value)?;
+        } else {
+            // separator between elements of the array
+            if count > 0 {
+                write!(output_file, ",")?;
+            }
+            write!(output_file, "\n{}", value)?;
+        }
+        count += 1;
+    }
+
+    if !options.lines {
+        writeln!(output_file, "\n]")?;
+    }
+
+    info!("Written {} annotations", count);
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
+use super::{MergeCommand, MergeScore};
+use anyhow::{Context, Result};
+use bio_rascal::gff::{Annotation, Phase, Strand};
+use itertools::Itertools;
+use log::info;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Returns the value if the same for all members
+fn common_value<T: PartialEq + Clone>(values: impl Iterator<Item = T>) -> Option<T> {
+    let mut result: Option<T> = None;
+    for value in values {
+        match &result {
+            None => result = Some(value),
+            Some(previous) if *previous != value => return None,
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Makes the annotation for a cluster, members are sorted by start
+fn merge_cluster(members: &[Annotation], options: &MergeCommand) -> Result<Annotation> {
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    for field in &options.attributes {
+        let values = members
+            .iter()
+            .filter_map(|member| get_field_value(member, field))
+            .flat_map(|value| {
+                value
+                    .split(',')
+                    .map(|value| value.trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|value| !value.is_empty())
+            .unique()
+            .join(",");
+        if !values.is_empty() {
+            attributes.insert(field.clone(), values);
+        }
+    }
+    attributes.insert(
+        options.members_attribute.clone(),
+        members
+            .iter()
+            .map(|member| member.uid.to_string())
+            .join(","),
+    );
+    attributes.insert("count".into(), members.len().to_string());
+
+    let scores = members.iter().map(|member| member.score);
+    let score = match options.score {
+        MergeScore::Max => scores.fold(f64::MIN, f64::max),
+        MergeScore::Min => scores.fold(f64::MAX, f64::min),
+        MergeScore::Count => members.len() as f64,
+    };
+
+    let strand =
+        common_value(members.iter().map(|member| member.strand.to_string())).unwrap_or(".".into());
+
+    Ok(Annotation {
+        seq_id: members[0].seq_id.clone(),
+        source: common_value(members.iter().map(|member| member.source.clone()))
+            .unwrap_or(".".into()),
+        feature_type: options.feature_type.clone(),
+        start: members[0].start,
+        end: members
+            .iter()
+            .map(|member| member.end)
+            .max()
+            .unwrap_or_default(),
+        score,
+        strand: Strand::from_value(&strand),
+        phase: Phase::from_value(".").context("Cannot parse Phase")?,
+        uid: Uuid::new_v4(),
+        attributes,
+        taxon_id: common_value(members.iter().map(|member| member.taxon_id)).unwrap_or(0),
+    })
+}
+
+pub fn merge_command(options: &MergeCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut annotations: Vec<Annotation> = gff_file.annotations().collect();
+    info!("Read {} annotations", annotations.len());
+    annotations.sort_by(|a, b| {
+        a.seq_id
+            .cmp(&b.seq_id)
+            .then(a.start.cmp(&b.start))
+            .then(a.end.cmp(&b.end))
+    });
+
+    if options.same_strand {
+        info!("Merging annotations on the same strand");
+    }
+
+    // open clusters for each sequence (and strand), with their end
+    let mut clusters: HashMap<(String, String), (Vec<Annotation>, u64)> = HashMap::new();
+    let mut merged: Vec<Annotation> = Vec::new();
+    for annotation in annotations {
+        let strand = match options.same_strand {
+            true => annotation.strand.to_string(),
+            false => String::new(),
+        };
+        let key = (annotation.seq_id.clone(), strand);
+        match clusters.get_mut(&key) {
+            Some((members, end)) if annotation.start <= *end + options.distance + 1 => {
+                *end = (*end).max(annotation.end);
+                members.push(annotation);
+            }
+            _ => {
+                let end = annotation.end;
+                if let Some((members, _)) = clusters.insert(key, (vec![annotation], end)) {
+                    merged.push(merge_cluster(&members, options)?);
+                }
+            }
+        }
+    }
+    for (members, _) in clusters.into_values() {
+        merged.push(merge_cluster(&members, options)?);
+    }
+    merged.sort_by(|a, b| {
+        a.seq_id
+            .cmp(&b.seq_id)
+            .then(a.start.cmp(&b.start))
+            .then(a.end.cmp(&b.end))
+    });
+
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+    for annotation in &merged {
+        writer.write(annotation)?;
+    }
+
+    info!("Written {} merged annotation(s)", merged.len());
+
+    writer.finish()
+}
@@ -1,11 +1,28 @@
 pub mod add;
+pub mod bed;
+pub mod blast;
+pub mod closest;
+pub mod complement;
+pub mod eggnog;
 pub mod fields;
+pub mod filter;
+pub mod flank;
+pub mod getseq;
+pub mod hmmer;
+pub mod import;
+pub mod interpro;
+pub mod intersect;
+pub mod json;
+pub mod merge;
 pub mod remove;
 pub mod table;
+pub mod taxon;
+pub mod taxonomy;
+pub mod translate;
 pub mod view;
 
 use anyhow::{bail, Result};
-use clap::{Args, Command, Parser, Subcommand};
+use clap::{Args, Command, Parser, Subcommand, ValueEnum};
 use clap_complete::{generate, Generator, Shell};
 use std::path::PathBuf;
 
@@ -32,10 +49,47 @@ pub enum Commands {
     Rm(RmCommand),
     View(ViewCommand),
     Table(TableCommand),
+    Eggnog(EggnogCommand),
+    Interpro(InterproCommand),
+    Blast(BlastCommand),
+    Hmmer(HmmerCommand),
+    Taxon(TaxonCommand),
+    Taxonomy(TaxonomyCommand),
+    Intersect(IntersectCommand),
+    Closest(ClosestCommand),
+    Merge(MergeCommand),
+    Complement(ComplementCommand),
+    Flank(FlankCommand),
     Gtf(GtfCommand),
-    // Json(JsonCommand),
-    // Import(ImportCommand),
-    // Filter(FilterCommand)
+    Genbank(GenbankCommand),
+    Json(JsonCommand),
+    Import(ImportCommand),
+    Filter(FilterCommand),
+    Getseq(GetseqCommand),
+    Translate(TranslateCommand),
+    Bed(BedCommand),
+}
+
+/// Options for the sections of a GFF file that are not annotations
+///
+/// By default comments, directives and the `##FASTA` section of the input
+/// are written to the output
+#[derive(Debug, Args)]
+pub struct GffOutputOptions {
+    /// Removes the `##FASTA` section from the output
+    ///
+    /// By default the sequences at the end of the GFF are kept unchanged
+    #[arg(long)]
+    pub strip_fasta: bool,
+    /// Writes the `##FASTA` section to a separate FASTA file
+    #[arg(long, conflicts_with = "strip_fasta")]
+    pub fasta_file: Option<PathBuf>,
+    /// Removes comments from the output
+    ///
+    /// Only lines starting with a single `#` are removed, directives
+    /// (starting with `##`, like `##sequence-region`) and `#!` lines are kept
+    #[arg(long)]
+    pub no_comments: bool,
 }
 
 fn key_value_parser(arg: &str) -> Result<(String, String)> {
@@ -66,6 +120,8 @@ pub struct AddCommand {
     /// One uid per line
     #[arg(short, long)]
     uid_file: Option<PathBuf>,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
     /// Input file, without value the stdin is used
     input_file: Option<PathBuf>,
     /// Output file, without value the stdout is used
@@ -96,6 +152,8 @@ pub struct RmCommand {
     /// The file needs to have a UID per line
     #[arg(short, long)]
     uid_file: Option<PathBuf>,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
     /// Input file, without value the stdin is used
     input_file: Option<PathBuf>,
     /// Output file, without value the stdout is used
@@ -171,15 +229,845 @@ pub struct TableCommand {
     /// Skips a number a lines from the table file
     #[arg(short, long, default_value_t = 0)]
     skip_rows: usize,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Adds the eggNOG-mapper annotations to a GFF file
+///
+/// Reads the `.emapper.annotations` file, using its header to find the
+/// columns requested, which are added as attributes with the same name.
+/// Missing values (`-`) are skipped and comma separated lists (like
+/// `KEGG_ko`, `GOs` and `PFAMs`) are added as multiple values. The query
+/// column is matched to the `key` or, with `--prodigal-gene`, to the
+/// sequence names of Prodigal (`contig_N`).
+#[derive(Debug, Args)]
+pub struct EggnogCommand {
+    /// The `.emapper.annotations` file written by eggNOG-mapper
+    #[arg(short, long, required = true)]
+    emapper_file: PathBuf,
+    /// Columns to add as attributes, by their name in the header
+    ///
+    /// Multiple columns can be passed, by using the option multiple times
+    /// or separating them by commas `,`
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "Preferred_name,Description,COG_category,KEGG_ko,PFAMs"
+    )]
+    columns: Vec<String>,
+    /// Field of the annotations matched to the query column
+    ///
+    /// Accepts the same fields as `view`
+    #[arg(short, long, default_value = "uid")]
+    key: String,
+    /// Use a key compatible with Prodigal sequence files
+    #[arg(short, long)]
+    prodigal_gene: bool,
+    /// Only output the modified annotations
+    #[arg(short, long)]
+    only_edited: bool,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Adds the InterProScan hits to a GFF file
+///
+/// Reads the TSV output of InterProScan, with one line per hit. The
+/// signatures of each analysis requested are added as an attribute with
+/// the analysis name (e.g. `Pfam`), as multiple values. The InterPro
+/// entries and GO terms of all hits are added as `InterPro` and
+/// `Ontology_term`. The hits of the analyses requested can also be written
+/// as `protein_match` children of the annotation, with the protein
+/// positions converted to coordinates on the sequence, using the strand and
+/// phase of the annotation.
+#[derive(Debug, Args)]
+pub struct InterproCommand {
+    /// The TSV file written by InterProScan
+    #[arg(short, long, required = true)]
+    interpro_file: PathBuf,
+    /// Analyses to use, as named in the 4th column
+    ///
+    /// Multiple analyses can be passed, by using the option multiple times
+    /// or separating them by commas `,`
+    #[arg(short, long, value_delimiter = ',', default_value = "Pfam")]
+    analyses: Vec<String>,
+    /// Writes each hit as a `protein_match` child of the annotation
+    ///
+    /// The child has the annotation `ID` (or `uid`) as `Parent`, the
+    /// signature as `Name` and the analysis as source. The parts of a CDS
+    /// (same `ID`, or `Parent` without one) are joined in transcription
+    /// order and a hit spanning several parts is written as one segment per
+    /// part, sharing the same `ID`
+    #[arg(short = 'm', long)]
+    protein_match: bool,
+    /// Field of the annotations matched to the protein accession
+    ///
+    /// Accepts the same fields as `view`
+    #[arg(short, long, default_value = "uid")]
+    key: String,
+    /// Use a key compatible with Prodigal sequence files
+    #[arg(short, long)]
+    prodigal_gene: bool,
+    /// Only output the modified annotations
+    #[arg(short, long)]
+    only_edited: bool,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// How to choose the best BLAST hit
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum BlastSort {
+    /// Highest bitscore, then lowest e-value
+    Bitscore,
+    /// Lowest e-value, then highest bitscore
+    Evalue,
+}
+
+/// Adds the best BLAST or DIAMOND hit to the annotations
+///
+/// Reads the tabular output (`outfmt 6`) and, for each query, chooses the
+/// best hit among the ones passing the thresholds. The subject, identity
+/// and e-value of the hit are added to the annotation matching the query.
+/// The query coverage needs either `qcovhsp` or `qstart`, `qend` and `qlen`
+/// in the output, with the `--fields` option set accordingly.
+#[derive(Debug, Args)]
+pub struct BlastCommand {
+    /// The tabular output of BLAST or DIAMOND
+    #[arg(short, long, required = true)]
+    blast_file: PathBuf,
+    /// Columns of the tabular output, as passed to `--outfmt 6`
+    ///
+    /// The default is the standard format. `qseqid`, `sseqid`, `pident`,
+    /// `evalue` and `bitscore` are required
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "qseqid,sseqid,pident,length,mismatch,gapopen,qstart,qend,sstart,send,evalue,bitscore"
+    )]
+    fields: Vec<String>,
+    /// How to choose the best hit
+    #[arg(short, long, value_enum, default_value_t = BlastSort::Bitscore)]
+    sort_by: BlastSort,
+    /// Minimum identity percentage of a hit
+    #[arg(short = 'i', long, default_value_t = 0.)]
+    min_identity: f64,
+    /// Minimum query coverage percentage of a hit
+    #[arg(short = 'c', long, default_value_t = 0.)]
+    min_coverage: f64,
+    /// Maximum e-value of a hit
+    #[arg(short = 'e', long)]
+    max_evalue: Option<f64>,
+    /// Attribute for the subject of the best hit
+    #[arg(long, default_value = "best_hit")]
+    subject_attribute: String,
+    /// Attribute for the identity of the best hit
+    #[arg(long, default_value = "best_hit_identity")]
+    identity_attribute: String,
+    /// Attribute for the e-value of the best hit
+    #[arg(long, default_value = "best_hit_evalue")]
+    evalue_attribute: String,
+    /// Field of the annotations matched to the query
+    ///
+    /// Accepts the same fields as `view`
+    #[arg(short, long, default_value = "uid")]
+    key: String,
+    /// Use a key compatible with Prodigal sequence files
+    #[arg(short, long)]
+    prodigal_gene: bool,
+    /// Only output the modified annotations
+    #[arg(short, long)]
+    only_edited: bool,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Format of the HMMER table
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum HmmerFormat {
+    /// Per sequence table, written with `--tblout`
+    Tblout,
+    /// Per domain table, written with `--domtblout`
+    Domtblout,
+}
+
+/// Adds the HMMER models assigned to the annotations
+///
+/// Reads the `--tblout` or `--domtblout` table of hmmsearch (or hmmscan)
+/// and assigns to each gene the models with a score above their threshold,
+/// either the best one or all of them, sorted by score. Thresholds for each
+/// model can be passed in a file, like the `ko_list` of KOfam, the
+/// `--min-score` is used for the other models. The full sequence score is
+/// used, unless the threshold file has `domain` as score type for a model.
+#[derive(Debug, Args)]
+pub struct HmmerCommand {
+    /// The table written by HMMER
+    #[arg(short = 'm', long, required = true)]
+    hmmer_file: PathBuf,
+    /// Format of the HMMER table
+    #[arg(short, long, value_enum, default_value_t = HmmerFormat::Domtblout)]
+    format: HmmerFormat,
+    /// The table was written by hmmscan, with the models as targets
+    #[arg(long)]
+    hmmscan: bool,
+    /// Uses the model accession instead of its name, if present
+    #[arg(long)]
+    use_accession: bool,
+    /// File with the threshold for each model
+    ///
+    /// Tab separated, with the model, its threshold and, optionally, the
+    /// score type (`full` or `domain`). Lines where the threshold is not a
+    /// number are skipped, so the KOfam `ko_list` file can be used directly
+    #[arg(short, long)]
+    thresholds_file: Option<PathBuf>,
+    /// Minimum score for models without a threshold in the file
+    #[arg(short = 's', long, default_value_t = 0.)]
+    min_score: f64,
+    /// Keeps all models above threshold, instead of the best one
+    #[arg(short = 'l', long)]
+    all: bool,
+    /// Attribute for the models
+    #[arg(short, long, default_value = "hmm_model")]
+    attribute: String,
+    /// Attribute for the scores of the models
+    #[arg(long)]
+    score_attribute: Option<String>,
+    /// Attribute for the e-values of the models
+    #[arg(long)]
+    evalue_attribute: Option<String>,
+    /// Field of the annotations matched to the gene name
+    ///
+    /// Accepts the same fields as `view`
+    #[arg(short, long, default_value = "uid")]
+    key: String,
+    /// Use a key compatible with Prodigal sequence files
+    #[arg(short, long)]
+    prodigal_gene: bool,
+    /// Only output the modified annotations
+    #[arg(short, long)]
+    only_edited: bool,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Format of the contig classification used by `taxon`
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TaxonFormat {
+    /// Kraken2 output, one line per sequence with the taxon ID in the 3rd
+    /// column
+    Kraken,
+    /// Tab separated table, with the contig and its taxon ID
+    Table,
+}
+
+/// Sets the taxon_id of annotations from the classification of contigs
+///
+/// Each annotation gets the taxon ID of its contig (`seq_id`), as found in
+/// the Kraken2 output or a two-column table. Contigs that are unclassified
+/// (taxon ID `0`) or not found are reported at the end.
+#[derive(Debug, Args)]
+pub struct TaxonCommand {
+    /// File with the classification of the contigs
+    #[arg(short, long, required = true)]
+    classification_file: PathBuf,
+    /// Format of the classification file
+    #[arg(short, long, value_enum, default_value_t = TaxonFormat::Kraken)]
+    format: TaxonFormat,
+    /// Only sets the taxon_id of annotations without one
+    #[arg(short = 'u', long)]
+    only_unset: bool,
+    /// Writes the contigs without a classification to a file, one per line
+    #[arg(short, long)]
+    report_file: Option<PathBuf>,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Filters annotations by clade and adds their lineage, using the NCBI
+/// taxonomy
+///
+/// The taxonomy is read from a local taxdump directory (`nodes.dmp`,
+/// `names.dmp` and, if present, `merged.dmp`). Annotations can be filtered
+/// to the ones whose taxon_id is in one of the clades passed, and the
+/// names of the taxa at some ranks or the full lineage can be added as
+/// attributes. Annotations without a taxon_id are not in any clade.
+#[derive(Debug, Args)]
+pub struct TaxonomyCommand {
+    /// Directory with the NCBI taxdump files
+    #[arg(short = 'd', long, required = true)]
+    taxdump: PathBuf,
+    /// Only keeps annotations in these clades, by taxon ID
+    ///
+    /// Multiple clades can be passed, by using the option multiple times
+    /// or separating them by commas `,`
+    #[arg(short, long, value_delimiter = ',')]
+    clades: Vec<u32>,
+    /// Removes the annotations in the clades instead
+    #[arg(short = 'v', long, requires = "clades")]
+    invert: bool,
+    /// Adds an attribute for each rank, with the name of the taxon at that
+    /// rank
+    ///
+    /// For example `phylum,genus,species`. Multiple ranks can be passed,
+    /// by using the option multiple times or separating them by commas `,`
+    #[arg(short, long, value_delimiter = ',')]
+    ranks: Vec<String>,
+    /// Adds the full lineage to this attribute
+    ///
+    /// The names of the taxa from the root are added as multiple values
+    #[arg(short, long)]
+    lineage: Option<String>,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Finds the annotations overlapping the features of another GFF or BED
+///
+/// By default only the annotations overlapping at least one feature are
+/// written, or the ones without overlaps with `--invert`. Fields of the
+/// overlapping features can be copied as attributes, and the overlapping
+/// pairs can be written as a table instead, with the fields requested for
+/// both and the length of the overlap.
+#[derive(Debug, Args)]
+pub struct IntersectCommand {
+    /// GFF (or BED) file with the features to overlap, can be gzipped
+    #[arg(short = 'b', long = "other", required = true)]
+    other_file: PathBuf,
+    /// The other file is a BED file
+    #[arg(long)]
+    bed: bool,
+    /// Writes the annotations without overlaps instead
+    #[arg(short = 'v', long)]
+    invert: bool,
+    /// Writes all annotations, used with `--copy-attributes`
+    #[arg(short, long, conflicts_with = "invert")]
+    keep_all: bool,
+    /// Minimum fraction of the annotation length that must overlap
+    #[arg(short, long, default_value_t = 0.)]
+    min_overlap: f64,
+    /// Only overlaps features on the same strand
+    #[arg(short, long)]
+    same_strand: bool,
+    /// Fields of the overlapping features added as attributes
+    ///
+    /// Accepts the same fields as `view`, the attribute name can be changed
+    /// with `field:attribute`, e.g. `uid:island_uid`. Values of multiple
+    /// features are added as multiple values. Multiple fields can be
+    /// passed, by using the option multiple times or separating them by
+    /// commas `,`
+    #[arg(short, long, value_delimiter = ',')]
+    copy_attributes: Vec<String>,
+    /// Writes a table with the overlapping pairs instead
+    #[arg(short, long)]
+    pairs: bool,
+    /// Fields written for each annotation in the table of pairs
+    ///
+    /// Accepts the same fields as `view`
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "seq_id,start,end,uid",
+        requires = "pairs"
+    )]
+    fields: Vec<String>,
+    /// Writes the header of the table of pairs
+    #[arg(short = 'e', long, requires = "pairs")]
+    header: bool,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Finds the nearest upstream and downstream neighbours of each annotation
+///
+/// The neighbours are searched in the same file or in another GFF or BED,
+/// upstream and downstream depend on the strand of the annotation and
+/// overlapping features are not considered neighbours. The distance is the
+/// number of bases between the two, negative for the upstream neighbour.
+/// The neighbours are added as the `upstream` and `downstream` attributes,
+/// with the distances in `upstream_distance` and `downstream_distance`, or
+/// written as a table instead, with the fields requested followed by the
+/// same 4 columns.
+#[derive(Debug, Args)]
+pub struct ClosestCommand {
+    /// GFF (or BED) file with the neighbours, can be gzipped
+    ///
+    /// Without value, the neighbours are the other annotations in the input
+    #[arg(short = 'b', long = "other")]
+    other_file: Option<PathBuf>,
+    /// The other file is a BED file
+    #[arg(long, requires = "other_file")]
+    bed: bool,
+    /// Only uses neighbours of this feature type
+    #[arg(short = 't', long)]
+    feature_type: Option<String>,
+    /// Only uses neighbours on the same strand
+    #[arg(short, long)]
+    same_strand: bool,
+    /// Field of the neighbours written, accepts the same fields as `view`
+    #[arg(short, long, default_value = "uid")]
+    name_field: String,
+    /// Writes a table with the neighbours instead
+    #[arg(short = 'p', long)]
+    table: bool,
+    /// Fields written for each annotation in the table
+    ///
+    /// Accepts the same fields as `view`
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "seq_id,start,end,uid",
+        requires = "table"
+    )]
+    fields: Vec<String>,
+    /// Writes the header of the table
+    #[arg(short = 'e', long, requires = "table")]
+    header: bool,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Score of the merged annotations
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum MergeScore {
+    /// Highest score of the members
+    Max,
+    /// Lowest score of the members
+    Min,
+    /// Number of members
+    Count,
+}
+
+/// Merges overlapping or nearby annotations into clusters
+///
+/// The annotations are sorted by `seq_id` and start and the ones that
+/// overlap, are book-ended or within `--distance` bases are written as a
+/// single annotation, with a new `uid`. The uids of the members are added
+/// as a list in the `members` attribute and their number in `count`, so the
+/// output can be used as a table for the `table` command. The strand,
+/// source and taxon_id are kept if the same for all members.
+#[derive(Debug, Args)]
+pub struct MergeCommand {
+    /// Maximum number of bases between annotations to merge them
+    #[arg(short, long, default_value_t = 0)]
+    distance: u64,
+    /// Only merges annotations on the same strand
+    #[arg(short, long)]
+    same_strand: bool,
+    /// Feature type of the merged annotations
+    #[arg(short = 't', long, default_value = "region")]
+    feature_type: String,
+    /// Score of the merged annotations
+    #[arg(short = 'c', long, value_enum, default_value_t = MergeScore::Max)]
+    score: MergeScore,
+    /// Attribute with the uids of the members
+    #[arg(short, long, default_value = "members")]
+    members_attribute: String,
+    /// Fields of the members added as attributes, with distinct values
+    ///
+    /// Accepts the same fields as `view`. Multiple fields can be passed, by
+    /// using the option multiple times or separating them by commas `,`
+    #[arg(short, long, value_delimiter = ',')]
+    attributes: Vec<String>,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Writes the intergenic regions, between the annotations
+///
+/// The sequence lengths are taken from the `##sequence-region` directives
+/// and from the `--lengths` file, if passed, and are used for the regions
+/// at the edges of each sequence, including the ones without annotations.
+/// Each `intergenic` annotation has a new `uid` and the flanking features
+/// in the `left_feature` and `right_feature` attributes, with their strands
+/// in `left_strand` and `right_strand`. When both are stranded, the
+/// `orientation` attribute is `convergent`, `divergent` or
+/// `co-directional`.
+#[derive(Debug, Args)]
+pub struct ComplementCommand {
+    /// Sequence lengths, as FASTA, FASTA index (`.fai`) or a table with
+    /// the sequence and its length, can be gzipped
+    #[arg(short, long)]
+    lengths: Option<PathBuf>,
+    /// Only uses annotations of these feature types
+    ///
+    /// Multiple feature types can be passed, by using the option multiple
+    /// times or separating them by commas `,`
+    #[arg(short = 't', long, value_delimiter = ',')]
+    feature_types: Vec<String>,
+    /// Minimum length of the intergenic regions
+    #[arg(short, long, default_value_t = 1)]
+    min_length: u64,
+    /// Field of the flanking features written, accepts the same fields as
+    /// `view`
+    #[arg(short, long, default_value = "uid")]
+    name_field: String,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Writes the regions upstream and/or downstream of the annotations
+///
+/// Upstream and downstream depend on the strand of each annotation, the
+/// ones without a strand are considered on the forward strand. The regions
+/// are written as `five_prime_flanking_region` and
+/// `three_prime_flanking_region`, with a new `uid`, the strand, source and
+/// taxon_id of the annotation and its `uid` in the `parent_uid` attribute.
+/// They are clipped to the start of the sequences and, if the length is
+/// known from the `##sequence-region` directives or the `--lengths` file,
+/// to their end.
+#[derive(Debug, Args)]
+pub struct FlankCommand {
+    /// Number of bases upstream
+    #[arg(short, long, default_value_t = 0)]
+    upstream: u64,
+    /// Number of bases downstream
+    #[arg(short, long, default_value_t = 0)]
+    downstream: u64,
+    /// Only uses annotations of these feature types
+    ///
+    /// Multiple feature types can be passed, by using the option multiple
+    /// times or separating them by commas `,`
+    #[arg(short = 't', long, value_delimiter = ',')]
+    feature_types: Vec<String>,
+    /// Sequence lengths, as FASTA, FASTA index (`.fai`) or a table with
+    /// the sequence and its length, can be gzipped
+    #[arg(short, long)]
+    lengths: Option<PathBuf>,
+    /// Attribute with the `uid` of the annotation
+    #[arg(short = 'a', long, default_value = "parent_uid")]
+    parent_attribute: String,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Filters a GFF file using an expression on the annotations fields
+///
+/// The expression can use the built-in fields (seq_id, source,
+/// feature_type, start, end, score, strand, phase, length, uid, taxon_id)
+/// and any attribute. Attributes with the same name as a built-in field
+/// must be prefixed by `attributes.`, e.g. `attributes.score`. Comparisons
+/// use `==`, `!=`, `<`, `<=`, `>`, `>=`, regular expressions are matched
+/// with `=~` and `!~` and can be written as `/regex/` or as a string.
+/// Comparisons with a number are numeric, with strings lexicographic.
+/// `has(field)` tests if a field is present and they can be combined
+/// with `&&` (`and`), `||` (`or`), `!` (`not`) and parenthesis.
+///
+/// Example: feature_type == "CDS" && length >= 300 && attributes.product =~ /kinase/
+#[derive(Debug, Args)]
+pub struct FilterCommand {
+    /// Expression used to filter the annotations
+    expression: String,
+    /// Outputs the annotations that do not match the expression
+    #[arg(short = 'v', long)]
+    invert: bool,
+    #[command(flatten)]
+    gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Exports a GFF as JSON or JSON Lines
+///
+/// Without attributes requested, each annotation is written with all
+/// built-in fields (seq_id, source, feature_type, start, end, score,
+/// strand, phase, uid, taxon_id) and an `attributes` object. If attributes
+/// are requested, only those are written, with `null` for missing values.
+#[derive(Debug, Args)]
+pub struct JsonCommand {
+    /// Attributes to write
+    ///
+    /// Multiple attributes can be passed, by using the option multiple times
+    /// or separating them by commas `,`. Accepts the same fields as `view`
+    #[arg(short, long, value_delimiter = ',')]
+    attributes: Vec<String>,
+    /// Writes JSON Lines, one object per line, instead of an array
+    #[arg(short, long)]
+    lines: bool,
+    /// Writes numeric fields (start, end, score, phase, length, taxon_id)
+    /// as numbers instead of strings
+    #[arg(short, long)]
+    numbers: bool,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Format of the file used by `import`
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// Tab separated table with a header, like the output of `view --header`
+    Tsv,
+    /// JSON Lines or a JSON array, like the output of `json`
+    Json,
+    /// BED file, with 3 to 6 standard columns and optional extra columns
+    Bed,
+}
+
+/// Builds a GFF file from a table or JSON
+///
+/// The inverse of `view` and `json`: the column names (or JSON keys) are
+/// the same fields accepted by `view`. `seq_id`, `start` and `end` are
+/// required, the other built-in fields (source, feature_type, score,
+/// strand, phase, taxon_id) are optional and any other field is added as an
+/// attribute. If `uid` is missing, a new one is generated.
+///
+/// BED files are converted to 1-based coordinates, with a new `uid` for
+/// each line, the name as an attribute and extra columns mapped to the
+/// attributes passed with `--columns`.
+#[derive(Debug, Args)]
+pub struct ImportCommand {
+    /// Format of the input file
+    #[arg(short, long, value_enum, default_value_t = ImportFormat::Tsv)]
+    format: ImportFormat,
+    /// Source used when not in the input
+    ///
+    /// The default is `.`
+    #[arg(short, long)]
+    source: Option<String>,
+    /// Feature type used when not in the input
+    ///
+    /// The default is `.`, or `region` for BED files
+    #[arg(short = 't', long)]
+    feature_type: Option<String>,
+    /// Attribute for the name column of a BED file
+    #[arg(short, long, default_value = "Name")]
+    name_attribute: String,
+    /// Attributes for the BED columns after the 6th (strand), in order
+    ///
+    /// Multiple attributes can be passed, by using the option multiple times
+    /// or separating them by commas `,`. The values are percent-encoded,
+    /// so lists like `blockSizes` are kept as a single value
+    #[arg(short, long, value_delimiter = ',')]
+    columns: Vec<String>,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// What to do when a line cannot be parsed
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ErrorMode {
+    /// Stops with an error
+    Fail,
+    /// Skips the line silently
+    Skip,
+    /// Skips the line, logging a warning
+    Warn,
+}
+
+/// Extracts the sequences of the annotations to a FASTA file
+///
+/// The sequences are taken from a FASTA file or, if not passed, from the
+/// `##FASTA` section of the GFF. Sequences of annotations on the minus
+/// strand are reverse complemented.
+#[derive(Debug, Args)]
+pub struct GetseqCommand {
+    /// FASTA file with the sequences, can be gzipped
+    ///
+    /// Without value, the `##FASTA` section of the GFF is used
+    #[arg(short, long)]
+    fasta_file: Option<PathBuf>,
+    /// Attributes used for the FASTA header
+    ///
+    /// The values are separated by spaces and missing ones skipped. Accepts
+    /// the same fields as `view`
+    #[arg(short, long, default_value = "uid", value_delimiter = ',')]
+    attributes: Vec<String>,
+    /// Number of bases to add on each side of the annotation
+    #[arg(short = 'l', long, default_value_t = 0)]
+    flank: usize,
+    /// Number of characters per line in the sequences, 0 to not wrap them
+    #[arg(short, long, default_value_t = 60)]
+    width: usize,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Translates CDS annotations to a protein FASTA file
+///
+/// The sequences are taken from a FASTA file or, if not passed, from the
+/// `##FASTA` section of the GFF. CDS with the same `Parent` are joined
+/// before translation and the `Phase` of the first one is honoured. The
+/// translation table can be set per annotation with a `transl_table`
+/// attribute.
+#[derive(Debug, Args)]
+pub struct TranslateCommand {
+    /// FASTA file with the sequences, can be gzipped
+    ///
+    /// Without value, the `##FASTA` section of the GFF is used
+    #[arg(short, long)]
+    fasta_file: Option<PathBuf>,
+    /// NCBI translation table used if the annotation has no `transl_table`
+    ///
+    /// 11 for bacteria and archaea, 4 for Mycoplasma, 1 is the standard code
+    #[arg(short, long, default_value_t = 11)]
+    table: u8,
+    /// Feature type of the annotations to translate
+    #[arg(short = 'y', long, default_value = "CDS")]
+    feature_type: String,
+    /// Attributes used for the FASTA header
+    ///
+    /// The values are separated by spaces and missing ones skipped. Accepts
+    /// the same fields as `view`; for joined CDS the first one is used
+    #[arg(short, long, default_value = "uid", value_delimiter = ',')]
+    attributes: Vec<String>,
+    /// Number of characters per line in the sequences, 0 to not wrap them
+    #[arg(short, long, default_value_t = 60)]
+    width: usize,
+    /// Input file, without value the stdin is used
+    input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    output_file: Option<PathBuf>,
+}
+
+/// Converts a GFF to BED6 or BED12
+///
+/// Coordinates are converted to 0-based, half open. In BED12 mode, only
+/// annotations with children (by `Parent`) of the block type are written,
+/// with the blocks from their children and the thick region from the
+/// children of the thick type.
+#[derive(Debug, Args)]
+pub struct BedCommand {
+    /// Field used as BED name
+    ///
+    /// Accepts the same fields as `view`
+    #[arg(short, long, default_value = "uid")]
+    name: String,
+    /// Writes BED12 for annotations with children
+    #[arg(short = '2', long)]
+    bed12: bool,
+    /// Feature type of the children used as blocks in BED12
+    #[arg(short, long, default_value = "exon")]
+    block_type: String,
+    /// Feature type of the children used for thick start/end in BED12
+    #[arg(short, long, default_value = "CDS")]
+    thick_type: String,
     /// Input file, without value the stdin is used
     input_file: Option<PathBuf>,
     /// Output file, without value the stdout is used
     output_file: Option<PathBuf>,
 }
 
+/// Converts a GTF file to GFF, or a GFF to GTF
+///
+/// When converting to GTF, `gene_id` and `transcript_id` are derived from
+/// the `ID`/`Parent` hierarchy of the GFF, unless already present, and the
+/// `uid` is kept as an attribute.
 #[derive(Args, Debug)]
 pub struct GtfCommand {
+    /// Converts a GFF to GTF instead
+    #[arg(short, long)]
+    pub reverse: bool,
+    /// What to do with malformed lines in a GTF file
+    ///
+    /// A count of the skipped lines is reported at the end
+    #[arg(short, long, value_enum, default_value_t = ErrorMode::Fail)]
+    pub on_error: ErrorMode,
+    /// Only used when writing GFF
+    #[command(flatten)]
+    pub gff_options: GffOutputOptions,
+    /// Input file, without value the stdin is used
     pub input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
+    pub output_file: Option<PathBuf>,
+}
+
+/// Converts a GenBank file to GFF, or a GFF to GenBank or EMBL
+///
+/// Qualifiers are added as attributes, with `db_xref`, `note` and
+/// `gene_synonym` renamed to `Dbxref`, `Note` and `Alias`; `translation`
+/// is skipped. Features with a `join()` location are written as one
+/// annotation per part, with the same `ID`, and features with the
+/// `locus_tag` of a gene have it as `Parent`. The `source` feature becomes
+/// a `region`.
+///
+/// When converting a GFF, the attributes are written back as qualifiers,
+/// except `ID`, `Parent`, `Name` and the other GFF3 reserved ones, and
+/// annotations with the same `ID` and feature type are joined. The `Phase`
+/// of a CDS is written as `/codon_start`. A record is written for each
+/// sequence, with a `source` feature added if no `region` spans it.
+#[derive(Args, Debug)]
+pub struct GenbankCommand {
+    /// Converts a GFF to GenBank instead
+    #[arg(short, long)]
+    pub reverse: bool,
+    /// Writes EMBL instead of GenBank, when converting a GFF
+    #[arg(short, long, requires = "reverse")]
+    pub embl: bool,
+    /// FASTA file with the sequences, can be gzipped
+    ///
+    /// Only used when converting a GFF. Without value, the `##FASTA`
+    /// section of the GFF is used
+    #[arg(long, requires = "reverse")]
+    pub fasta_file: Option<PathBuf>,
+    /// Source used for the annotations
+    #[arg(short, long, default_value = "GenBank")]
+    pub source: String,
+    /// Writes the sequences of the records as a `##FASTA` section
+    #[arg(short, long, conflicts_with = "reverse")]
+    pub fasta: bool,
+    /// Input file, without value the stdin is used
+    pub input_file: Option<PathBuf>,
+    /// Output file, without value the stdout is used
     pub output_file: Option<PathBuf>,
 }
 
@@ -1,7 +1,7 @@
+use super::super::gff::{GffFile, GffWriter};
 use super::super::utils::{file_or_stdin, file_or_stdout};
 use super::TableCommand;
 use anyhow::{bail, Result};
-use bio_rascal::gff::GffReader;
 use bio_rascal::io::open_file;
 use log::{info, warn};
 use std::collections::HashMap;
@@ -58,7 +58,7 @@ fn read_table<P: AsRef<Path>, C: AsRef<str>>(
 pub fn table_command(options: &TableCommand) -> Result<()> {
     // first check the input and output files
     let input_file = file_or_stdin(&options.input_file)?;
-    let mut output_file = file_or_stdout(&options.output_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
 
     info!("Reading table from file {}", &options.table_file.display());
     let value_table = read_table(
@@ -83,9 +83,10 @@ pub fn table_command(options: &TableCommand) -> Result<()> {
         );
     }
 
-    let reader = GffReader::from_reader(input_file);
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
 
-    for mut annotation in reader {
+    for mut annotation in gff_file.annotations() {
         // check if the key is in the value_table
         let key_value = if options.prodigal_gene {
             match annotation.get_attr("ID") {
@@ -94,10 +95,7 @@ pub fn table_command(options: &TableCommand) -> Result<()> {
             }
         // gets the value from the attributes
         } else {
-            match annotation.get_attr(&key) {
-                None => String::new(),
-                Some(value) => value,
-            }
+            annotation.get_attr(&key).unwrap_or_default()
         };
         match value_table.get(&key_value) {
             Some(value_vec) => {
@@ -118,8 +116,8 @@ pub fn table_command(options: &TableCommand) -> Result<()> {
         };
 
         // Writes to the output file
-        writeln!(output_file, "{}", annotation.to_string())?;
+        writer.write(&annotation)?;
     }
 
-    Ok(())
+    writer.finish()
 }
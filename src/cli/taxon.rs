@@ -0,0 +1,114 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::utils::{file_or_stdin, file_or_stdout};
+use super::{TaxonCommand, TaxonFormat};
+use anyhow::{bail, Context, Result};
+use bio_rascal::io::open_file;
+use bio_rascal::taxon::ROOT_TAXON;
+use log::{info, warn};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// Taxon ID for each contig, `0` for unclassified ones
+type Classification = HashMap<String, String>;
+
+/// Extracts the taxon ID from the Kraken2 column, which can include the
+/// name if `--use-names` was used, e.g. `Escherichia coli (taxid 562)`
+fn kraken_taxon_id(value: &str) -> &str {
+    match value.rsplit_once("(taxid ") {
+        None => value.trim(),
+        Some((_, taxon_id)) => taxon_id.trim_end_matches(')').trim(),
+    }
+}
+
+/// Reads the contig classification, either the Kraken2 output (status,
+/// sequence, taxon ID, ...) or a two-column table (sequence and taxon ID)
+fn read_classification<P: AsRef<Path>>(
+    file_name: P,
+    format: TaxonFormat,
+) -> Result<Classification> {
+    let file_handle = open_file(file_name.as_ref())?;
+
+    let mut classification = Classification::new();
+    for (index, line) in file_handle.lines().enumerate() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (seq_id, taxon_id) = match (format, fields.len()) {
+            (TaxonFormat::Kraken, 3..) => (fields[1], kraken_taxon_id(fields[2])),
+            (TaxonFormat::Table, 2..) => (fields[0], fields[1].trim()),
+            _ => bail!("Line {} has {} columns", index + 1, fields.len()),
+        };
+        classification.insert(seq_id.trim().to_string(), taxon_id.to_string());
+    }
+
+    info!(
+        "Read the classification of {} contig(s)",
+        classification.len()
+    );
+
+    Ok(classification)
+}
+
+pub fn taxon_command(options: &TaxonCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    info!(
+        "Reading contig classification from file {}",
+        options.classification_file.display()
+    );
+    let classification = read_classification(&options.classification_file, options.format)
+        .context("Cannot read the classification file")?;
+
+    if options.only_unset {
+        info!("Only setting annotations without a taxon_id");
+    }
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    // contigs without a classification, either missing or unclassified
+    let mut unclassified: BTreeSet<String> = BTreeSet::new();
+    let mut count = 0;
+    let mut edited = 0;
+    for mut annotation in gff_file.annotations() {
+        count += 1;
+        match classification.get(&annotation.seq_id) {
+            Some(taxon_id) if taxon_id != "0" => {
+                if !(options.only_unset && annotation.taxon_id >= ROOT_TAXON) {
+                    annotation.taxon_id = taxon_id.parse().with_context(|| {
+                        format!("Cannot convert taxon_id '{}' to a number", taxon_id)
+                    })?;
+                    edited += 1;
+                }
+            }
+            _ => {
+                if !unclassified.contains(&annotation.seq_id) {
+                    unclassified.insert(annotation.seq_id.clone());
+                }
+            }
+        }
+        writer.write(&annotation)?;
+    }
+
+    info!("Set the taxon_id of {} of {} annotation(s)", edited, count);
+    if !unclassified.is_empty() {
+        warn!("{} contig(s) without a classification", unclassified.len());
+    }
+    if let Some(report_file) = &options.report_file {
+        info!(
+            "Writing contigs without a classification to {}",
+            report_file.display()
+        );
+        let mut report_file = file_or_stdout(&Some(report_file.clone()))?;
+        for seq_id in &unclassified {
+            writeln!(report_file, "{}", seq_id)?;
+        }
+    }
+
+    writer.finish()
+}
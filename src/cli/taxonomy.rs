@@ -0,0 +1,169 @@
+use super::super::gff::{GffFile, GffWriter};
+use super::super::taxonomy::Taxonomy;
+use super::super::utils::{encode_attribute_value, file_or_stdin, file_or_stdout};
+use super::TaxonomyCommand;
+use anyhow::{bail, Result};
+use bio_rascal::gff::Annotation;
+use bio_rascal::taxon::ROOT_TAXON;
+use log::{info, warn};
+
+/// Adds the names of the taxa at `ranks` and, if `lineage` is passed, the
+/// names of the lineage from the root, as a multi-value attribute. The
+/// names are percent-encoded, since they can contain `,` or `;`.
+fn add_names(
+    annotation: &mut Annotation,
+    taxonomy: &Taxonomy,
+    ranks: &[String],
+    lineage: Option<&str>,
+) {
+    let taxon_id = annotation.taxon_id;
+    for rank in ranks {
+        if let Some(name) = taxonomy
+            .taxon_at_rank(taxon_id, rank)
+            .and_then(|rank_id| taxonomy.name(rank_id))
+        {
+            annotation
+                .attributes
+                .insert(rank.clone(), encode_attribute_value(name));
+        }
+    }
+    if let Some(attribute) = lineage {
+        let names: Vec<String> = taxonomy
+            .lineage(taxon_id)
+            .into_iter()
+            .rev()
+            .filter_map(|lineage_id| taxonomy.name(lineage_id))
+            .map(encode_attribute_value)
+            .collect();
+        annotation
+            .attributes
+            .insert(attribute.to_string(), names.join(","));
+    }
+}
+
+pub fn taxonomy_command(options: &TaxonomyCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let output_file = file_or_stdout(&options.output_file)?;
+
+    if options.clades.is_empty() && options.ranks.is_empty() && options.lineage.is_none() {
+        bail!("At least one of --clades, --ranks or --lineage is required");
+    }
+
+    let taxonomy = Taxonomy::from_taxdump(&options.taxdump)?;
+    for clade in &options.clades {
+        if !taxonomy.contains(*clade) {
+            bail!("Clade {} not found in the taxonomy", clade);
+        }
+        info!(
+            "Keeping annotations in clade {} ({})",
+            clade,
+            taxonomy.name(*clade).unwrap_or_default()
+        );
+    }
+
+    let gff_file = GffFile::from_reader(input_file);
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
+
+    let mut count = 0;
+    let mut written = 0;
+    let mut not_found = 0;
+    for mut annotation in gff_file.annotations() {
+        count += 1;
+        let taxon_id = annotation.taxon_id;
+        let found = taxon_id >= ROOT_TAXON && taxonomy.contains(taxon_id);
+        if taxon_id >= ROOT_TAXON && !found {
+            not_found += 1;
+        }
+
+        if !options.clades.is_empty() {
+            let in_clade = found
+                && options
+                    .clades
+                    .iter()
+                    .any(|clade| taxonomy.is_descendant(taxon_id, *clade));
+            if in_clade == options.invert {
+                continue;
+            }
+        }
+
+        if found {
+            add_names(
+                &mut annotation,
+                &taxonomy,
+                &options.ranks,
+                options.lineage.as_deref(),
+            );
+        }
+
+        writer.write(&annotation)?;
+        written += 1;
+    }
+
+    info!("Written {} of {} annotation(s)", written, count);
+    if not_found > 0 {
+        warn!(
+            "{} annotation(s) with a taxon_id not found in the taxonomy",
+            not_found
+        );
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::utils::split_attribute_values;
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn names_are_encoded() {
+        let directory =
+            std::env::temp_dir().join(format!("gff-utils-taxdump-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("nodes.dmp"),
+            "1\t|\t1\t|\tno rank\t|\n2\t|\t1\t|\tgenus\t|\n3\t|\t2\t|\tspecies\t|\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("names.dmp"),
+            "1\t|\troot\t|\t\t|\tscientific name\t|\n\
+             2\t|\tCandidatus Genus, group; A\t|\t\t|\tscientific name\t|\n\
+             3\t|\tbacterium K=1\t|\t\t|\tscientific name\t|\n",
+        )
+        .unwrap();
+        let taxonomy = Taxonomy::from_taxdump(&directory).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let mut annotation = Annotation {
+            seq_id: "seq1".into(),
+            source: ".".into(),
+            feature_type: "CDS".into(),
+            start: 1,
+            end: 99,
+            score: 0.,
+            strand: bio_rascal::gff::Strand::from_value("+"),
+            phase: bio_rascal::gff::Phase::from_value("0").unwrap(),
+            uid: uuid::Uuid::new_v4(),
+            attributes: HashMap::new(),
+            taxon_id: 3,
+        };
+        add_names(
+            &mut annotation,
+            &taxonomy,
+            &["genus".to_string()],
+            Some("lineage"),
+        );
+
+        assert_eq!(
+            annotation.attributes["genus"],
+            "Candidatus Genus%2C group%3B A"
+        );
+        assert_eq!(
+            split_attribute_values(&annotation.attributes["lineage"]),
+            vec!["Candidatus Genus, group; A", "bacterium K=1"]
+        );
+    }
+}
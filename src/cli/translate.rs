@@ -0,0 +1,108 @@
+use super::super::fasta::{read_fasta, reverse_complement, subsequence, write_fasta};
+use super::super::genetic_code::GeneticCode;
+use super::super::gff::GffFile;
+use super::super::utils::{file_or_stdin, file_or_stdout, is_minus_strand, join_field_values};
+use super::TranslateCommand;
+use anyhow::{Context, Result};
+use bio_rascal::gff::Annotation;
+use bio_rascal::io::open_file_base;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::io::BufReader;
+
+pub fn translate_command(options: &TranslateCommand) -> Result<()> {
+    // first check the input and output files
+    let input_file = file_or_stdin(&options.input_file)?;
+    let mut output_file = file_or_stdout(&options.output_file)?;
+
+    let default_code = GeneticCode::from_table(options.table)?;
+    info!("Using translation table {} by default", options.table);
+
+    let gff_file = GffFile::from_reader(input_file);
+
+    // Annotations grouped by Parent, in the order they are found. Those
+    // without a Parent are translated on their own
+    let mut groups: Vec<Vec<Annotation>> = Vec::new();
+    let mut parents: HashMap<String, usize> = HashMap::new();
+    for annotation in gff_file.annotations() {
+        if annotation.feature_type != options.feature_type {
+            continue;
+        }
+        match annotation.get_attr("Parent") {
+            None => groups.push(vec![annotation]),
+            Some(parent) => match parents.get(&parent) {
+                Some(index) => groups[*index].push(annotation),
+                None => {
+                    parents.insert(parent, groups.len());
+                    groups.push(vec![annotation]);
+                }
+            },
+        }
+    }
+    info!("Found {} {} to translate", groups.len(), options.feature_type);
+
+    let sequences = match &options.fasta_file {
+        Some(fasta_file) => {
+            info!("Reading sequences from file {}", fasta_file.display());
+            read_fasta(BufReader::new(open_file_base(fasta_file)?))?
+        }
+        None => {
+            info!("Using sequences in the ##FASTA section");
+            gff_file.read_sequences()?
+        }
+    };
+
+    let mut missing = 0;
+    for mut group in groups {
+        // parts are joined from 5' to 3'
+        let minus = is_minus_strand(&group[0]);
+        group.sort_by_key(|annotation| annotation.start);
+        if minus {
+            group.reverse();
+        }
+        let first = &group[0];
+
+        let sequence = match sequences.get(&first.seq_id) {
+            None => {
+                missing += 1;
+                continue;
+            }
+            Some(sequence) => sequence,
+        };
+        let mut cds: Vec<u8> = Vec::new();
+        for part in &group {
+            let part_sequence = subsequence(sequence, part.start as usize, part.end as usize, 0);
+            if minus {
+                cds.extend(reverse_complement(part_sequence));
+            } else {
+                cds.extend_from_slice(part_sequence);
+            }
+        }
+
+        let code = match first.get_attr("transl_table") {
+            None => default_code,
+            Some(value) => GeneticCode::from_table(
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid transl_table value: {}", value))?,
+            )?,
+        };
+        // the phase of the first part is the number of bases to skip
+        let phase = first
+            .phase
+            .to_string()
+            .parse::<usize>()
+            .unwrap_or(0)
+            .min(cds.len());
+        let protein = code.translate(&cds[phase..], phase == 0);
+
+        let header = join_field_values(first, &options.attributes, " ");
+        write_fasta(&mut output_file, &header, &protein, options.width)?;
+    }
+
+    if missing > 0 {
+        warn!("Sequence not found for {} annotation(s)", missing);
+    }
+
+    Ok(())
+}
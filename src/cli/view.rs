@@ -1,8 +1,7 @@
-use super::super::utils::{file_or_stdin, file_or_stdout};
+use super::super::utils::{file_or_stdin, file_or_stdout, get_field_value};
 use super::ViewCommand;
 use anyhow::Result;
 use bio_rascal::gff::GffReader;
-use bio_rascal::taxon::ROOT_TAXON;
 use itertools::Itertools;
 use log::info;
 
@@ -27,30 +26,14 @@ pub fn view_command(options: &ViewCommand) -> Result<()> {
         let mut values: Vec<String> = Vec::new();
 
         for attribute in &options.attributes {
-            let value = match attribute.as_str() {
-                "uid" => annotation.uid.to_string(),
-                "taxon_id" => match annotation.taxon_id {
-                    ROOT_TAXON.. => annotation.taxon_id.to_string(),
-                    _ => "".into(),
-                },
-                "seq_id" => annotation.seq_id.clone(),
-                "source" => annotation.source.clone(),
-                "feature_type" => annotation.feature_type.clone(),
-                "start" => annotation.start.to_string(),
-                "end" => annotation.end.to_string(),
-                "score" => annotation.score.to_string(),
-                "strand" => annotation.strand.to_string(),
-                "phase" => annotation.phase.to_string(),
-                "length" => annotation.length().to_string(),
-                _ => match annotation.attributes.get(attribute) {
-                    Some(value) => value.clone(),
-                    None => {
-                        if options.keep_empty {
-                            "".into()
-                        } else {
-                            continue;
-                        }
-                    },
+            let value = match get_field_value(&annotation, attribute) {
+                Some(value) => value,
+                None => {
+                    if options.keep_empty {
+                        "".into()
+                    } else {
+                        continue;
+                    }
                 },
             };
             values.push(value);
@@ -0,0 +1,138 @@
+use anyhow::{bail, Result};
+use log::info;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// Sequences from a FASTA file, the key is the header up to the first space
+pub type Sequences = HashMap<String, Vec<u8>>;
+
+/// Length of each sequence
+pub type SequenceLengths = HashMap<String, u64>;
+
+/// Reads all sequences in a FASTA file into memory
+pub fn read_fasta<R: BufRead>(reader: R) -> Result<Sequences> {
+    let mut sequences = Sequences::new();
+    let mut current: Option<(String, Vec<u8>)> = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(header) = line.strip_prefix('>') {
+            if let Some((seq_id, sequence)) = current.take() {
+                sequences.insert(seq_id, sequence);
+            }
+            let seq_id = header.split_whitespace().next().unwrap_or_default();
+            current = Some((seq_id.to_string(), Vec::new()));
+        } else if let Some((_, sequence)) = current.as_mut() {
+            sequence.extend(line.trim().bytes());
+        }
+    }
+    if let Some((seq_id, sequence)) = current {
+        sequences.insert(seq_id, sequence);
+    }
+
+    info!("Read {} sequence(s)", sequences.len());
+
+    Ok(sequences)
+}
+
+/// Reads the length of each sequence from a FASTA file, a FASTA index
+/// (`.fai`) or a table with the sequence and its length as the first two
+/// columns. The format is detected from the first character.
+pub fn read_lengths<R: BufRead>(reader: R) -> Result<SequenceLengths> {
+    let mut lengths = SequenceLengths::new();
+    let mut current: Option<(String, u64)> = None;
+    let mut is_fasta = false;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if index == 0 {
+            is_fasta = line.starts_with('>');
+        }
+        if is_fasta {
+            if let Some(header) = line.strip_prefix('>') {
+                if let Some((seq_id, length)) = current.take() {
+                    lengths.insert(seq_id, length);
+                }
+                let seq_id = header.split_whitespace().next().unwrap_or_default();
+                current = Some((seq_id.to_string(), 0));
+            } else if let Some((_, length)) = current.as_mut() {
+                *length += line.trim().len() as u64;
+            }
+        } else if !line.starts_with('#') && !line.trim().is_empty() {
+            let mut fields = line.split('\t');
+            match (fields.next(), fields.next().map(|value| value.trim().parse::<u64>())) {
+                (Some(seq_id), Some(Ok(length))) => {
+                    lengths.insert(seq_id.trim().to_string(), length);
+                }
+                _ => bail!("Cannot read the sequence length at line {}", index + 1),
+            }
+        }
+    }
+    if let Some((seq_id, length)) = current {
+        lengths.insert(seq_id, length);
+    }
+
+    info!("Read the length of {} sequence(s)", lengths.len());
+
+    Ok(lengths)
+}
+
+/// Writes a FASTA record, wrapping the sequence to `width` characters per
+/// line. A `width` of 0 writes the sequence on one line.
+pub fn write_fasta(writer: &mut dyn Write, header: &str, sequence: &[u8], width: usize) -> Result<()> {
+    writeln!(writer, ">{}", header)?;
+    if width == 0 {
+        writer.write_all(sequence)?;
+        writeln!(writer)?;
+    } else {
+        for chunk in sequence.chunks(width) {
+            writer.write_all(chunk)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the sequence between `start` and `end` (1-based, inclusive),
+/// extended by `flank` bases on each side and clipped to the sequence length
+pub fn subsequence(sequence: &[u8], start: usize, end: usize, flank: usize) -> &[u8] {
+    let start = start.saturating_sub(1 + flank).min(sequence.len());
+    let end = end.saturating_add(flank).min(sequence.len()).max(start);
+    &sequence[start..end]
+}
+
+/// Returns the reverse complement of a nucleotide sequence, keeping the
+/// case. IUPAC ambiguity codes are complemented, other characters are kept
+pub fn reverse_complement(sequence: &[u8]) -> Vec<u8> {
+    sequence
+        .iter()
+        .rev()
+        .map(|base| match base {
+            b'A' => b'T',
+            b'T' | b'U' => b'A',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'a' => b't',
+            b't' | b'u' => b'a',
+            b'c' => b'g',
+            b'g' => b'c',
+            b'R' => b'Y',
+            b'Y' => b'R',
+            b'K' => b'M',
+            b'M' => b'K',
+            b'B' => b'V',
+            b'V' => b'B',
+            b'D' => b'H',
+            b'H' => b'D',
+            b'r' => b'y',
+            b'y' => b'r',
+            b'k' => b'm',
+            b'm' => b'k',
+            b'b' => b'v',
+            b'v' => b'b',
+            b'd' => b'h',
+            b'h' => b'd',
+            other => *other,
+        })
+        .collect()
+}
@@ -0,0 +1,1065 @@
+use super::cli::GenbankCommand;
+use super::fasta::{read_fasta, write_fasta, Sequences};
+use super::gff::GffFile;
+use super::utils::{
+    decode_attribute_value, encode_attribute_value, file_or_stdin, file_or_stdout, is_minus_strand,
+    split_attribute_values,
+};
+use anyhow::{bail, Context, Result};
+use bio_rascal::gff::{Annotation, Phase, Strand};
+use bio_rascal::io::open_file_base;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use uuid::Uuid;
+
+/// Qualifiers renamed to the equivalent GFF3 attribute
+const QUALIFIERS_MAP: [(&str, &str); 3] = [
+    ("db_xref", "Dbxref"),
+    ("note", "Note"),
+    ("gene_synonym", "Alias"),
+];
+
+/// GFF3 attributes that are not written as qualifiers, the last ones are
+/// the partial markers of the location
+const SKIPPED_ATTRIBUTES: [&str; 11] = [
+    "ID",
+    "Parent",
+    "Name",
+    "Target",
+    "Gap",
+    "Derives_from",
+    "Ontology_term",
+    "Is_circular",
+    "partial",
+    "start_range",
+    "end_range",
+];
+
+/// Attributes with multiple values, written as one qualifier per value
+const MULTI_VALUE_ATTRIBUTES: [&str; 2] = ["Dbxref", "Alias"];
+
+/// Qualifiers with a numeric value, written without quotes
+const NUMERIC_QUALIFIERS: [&str; 4] = ["codon_start", "transl_table", "number", "estimated_length"];
+
+/// Qualifiers without a value, set to `true` in the attributes
+const FLAG_QUALIFIERS: [&str; 10] = [
+    "pseudo",
+    "ribosomal_slippage",
+    "trans_splicing",
+    "environmental_sample",
+    "focus",
+    "germline",
+    "macronuclear",
+    "proviral",
+    "rearranged",
+    "transgenic",
+];
+
+/// Maximum number of characters of location and qualifiers in a line of
+/// the feature table
+const FEATURE_WIDTH: usize = 58;
+
+/// Feature of a GenBank record, with the location as found in the file
+pub struct Feature {
+    pub key: String,
+    pub location: String,
+    pub qualifiers: Vec<(String, String)>,
+}
+
+/// Record of a GenBank file
+pub struct Record {
+    /// Accession and version if present, otherwise the LOCUS name
+    pub seq_id: String,
+    pub features: Vec<Feature>,
+    pub sequence: Vec<u8>,
+}
+
+/// Part of a location, with 1-based coordinates
+#[derive(Debug, PartialEq)]
+pub struct LocationPart {
+    pub start: u64,
+    pub end: u64,
+    pub minus: bool,
+    /// The start is partial (`<`)
+    pub partial_start: bool,
+    /// The end is partial (`>`)
+    pub partial_end: bool,
+}
+
+/// Splits the arguments of `join`/`order` on the commas that are not
+/// inside parenthesis
+fn split_location_arguments(arguments: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut last = 0;
+    for (position, c) in arguments.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&arguments[last..position]);
+                last = position + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&arguments[last..]);
+    parts
+}
+
+/// Parses a GenBank location, like `complement(join(1..100,200..>300))`,
+/// returning the parts in the order they are joined (5' to 3' of the
+/// feature). Partial markers (`<`, `>`) refer to the coordinates as
+/// written, regardless of the strand. Sites between two bases (`^`) are
+/// returned as the two bases and parts referring to other records are
+/// skipped.
+pub fn parse_location(location: &str) -> Result<Vec<LocationPart>> {
+    let location = location.trim();
+    if let Some(inner) = location
+        .strip_prefix("complement(")
+        .and_then(|inner| inner.strip_suffix(')'))
+    {
+        let mut parts = parse_location(inner)?;
+        parts.reverse();
+        for part in parts.iter_mut() {
+            part.minus = !part.minus;
+        }
+        return Ok(parts);
+    }
+    for operator in ["join(", "order("] {
+        if let Some(inner) = location
+            .strip_prefix(operator)
+            .and_then(|inner| inner.strip_suffix(')'))
+        {
+            let mut parts = Vec::new();
+            for argument in split_location_arguments(inner) {
+                parts.extend(parse_location(argument)?);
+            }
+            return Ok(parts);
+        }
+    }
+    if location.contains(':') {
+        warn!("Skipping location part in another record: {}", location);
+        return Ok(Vec::new());
+    }
+    let (start, end) = match location
+        .split_once("..")
+        .or_else(|| location.split_once('^'))
+    {
+        Some((start, end)) => (start, end),
+        None => (location, location),
+    };
+    let parse_position = |position: &str| -> Result<u64> {
+        position
+            .trim_start_matches(['<', '>'])
+            .parse()
+            .with_context(|| format!("Cannot parse location: {}", location))
+    };
+    Ok(vec![LocationPart {
+        start: parse_position(start)?,
+        end: parse_position(end)?,
+        minus: false,
+        partial_start: start.starts_with('<'),
+        partial_end: end.starts_with('>'),
+    }])
+}
+
+/// Reads the records of a GenBank file
+pub struct GenbankReader {
+    reader: BufReader<Box<dyn Read>>,
+    line_number: usize,
+}
+
+impl GenbankReader {
+    pub fn from_reader(reader: Box<dyn Read>) -> Self {
+        GenbankReader {
+            reader: BufReader::new(reader),
+            line_number: 0,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>> {
+        let mut buffer = String::new();
+        let mut locus: Option<String> = None;
+        let mut version: Option<String> = None;
+        let mut features: Vec<Feature> = Vec::new();
+        let mut sequence: Vec<u8> = Vec::new();
+        let mut in_features = false;
+        let mut in_origin = false;
+
+        loop {
+            buffer.clear();
+            self.line_number += 1;
+            if self.reader.read_line(&mut buffer)? == 0 {
+                if locus.is_some() {
+                    bail!("Record not terminated by //");
+                }
+                return Ok(None);
+            }
+            let line = buffer.trim_end();
+
+            if line.starts_with("//") {
+                break;
+            } else if let Some(rest) = line.strip_prefix("LOCUS") {
+                locus = rest.split_whitespace().next().map(String::from);
+                in_features = false;
+                in_origin = false;
+            } else if let Some(rest) = line.strip_prefix("VERSION") {
+                version = rest.split_whitespace().next().map(String::from);
+            } else if line.starts_with("FEATURES") {
+                in_features = true;
+            } else if line.starts_with("ORIGIN") {
+                in_features = false;
+                in_origin = true;
+            } else if in_origin {
+                sequence.extend(line.bytes().filter(|c| c.is_ascii_alphabetic()));
+            } else if in_features {
+                if !line.starts_with(' ') {
+                    // another section, like CONTIG
+                    in_features = false;
+                } else if line.len() > 5 && !line[5..].starts_with(' ') {
+                    let (key, location) = line[5..]
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((&line[5..], ""));
+                    features.push(Feature {
+                        key: key.to_string(),
+                        location: location.trim().to_string(),
+                        qualifiers: Vec::new(),
+                    });
+                } else if let Some(feature) = features.last_mut() {
+                    let text = line.trim();
+                    if let Some(qualifier) = text.strip_prefix('/') {
+                        let (key, value) = qualifier.split_once('=').unwrap_or((qualifier, ""));
+                        feature
+                            .qualifiers
+                            .push((key.to_string(), value.to_string()));
+                    } else if let Some((key, value)) = feature.qualifiers.last_mut() {
+                        // continuation of a qualifier value
+                        if key != "translation" {
+                            value.push(' ');
+                        }
+                        value.push_str(text);
+                    } else {
+                        // continuation of the location
+                        feature.location.push_str(text);
+                    }
+                }
+            }
+        }
+
+        let seq_id = match version.or(locus) {
+            None => bail!("Record without LOCUS"),
+            Some(seq_id) => seq_id,
+        };
+        // removes quotes from qualifier values
+        for feature in features.iter_mut() {
+            for (_, value) in feature.qualifiers.iter_mut() {
+                if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                    *value = value[1..value.len() - 1].replace("\"\"", "\"");
+                }
+            }
+        }
+
+        Ok(Some(Record {
+            seq_id,
+            features,
+            sequence,
+        }))
+    }
+}
+
+impl Iterator for GenbankReader {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line_number = self.line_number;
+        self.read_record()
+            .with_context(|| format!("Cannot read record starting at line {}", line_number + 1))
+            .transpose()
+    }
+}
+
+/// Converts the qualifiers of a feature to GFF attributes. Values are
+/// percent-encoded and repeated qualifiers are joined with commas,
+/// qualifiers without a value are set to `true`. `translation` is skipped.
+fn qualifiers_to_attributes(qualifiers: &[(String, String)]) -> HashMap<String, String> {
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    for (key, value) in qualifiers {
+        if key == "translation" {
+            continue;
+        }
+        let key = match QUALIFIERS_MAP
+            .iter()
+            .find(|(qualifier, _)| qualifier == key)
+        {
+            Some((_, attribute)) => attribute.to_string(),
+            None => key.clone(),
+        };
+        let value = if value.is_empty() {
+            "true".to_string()
+        } else {
+            encode_attribute_value(value)
+        };
+        match attributes.get_mut(&key) {
+            Some(current) => {
+                current.push(',');
+                current.push_str(&value);
+            }
+            None => _ = attributes.insert(key, value),
+        }
+    }
+    attributes
+}
+
+/// Converts a record to annotations. Features with multiple parts share
+/// the same `ID`, made unique with a number if already used by another
+/// feature; features with the same `locus_tag` of a gene have it as
+/// `Parent`. Partial features have `partial=true` and the partial ends of
+/// the parts are in `start_range` and `end_range`, like in NCBI files.
+fn record_to_annotations(record: &Record, source: &str) -> Result<Vec<Annotation>> {
+    let mut annotations: Vec<Annotation> = Vec::new();
+    let mut genes: HashSet<String> = HashSet::new();
+    let mut ids: HashSet<String> = HashSet::new();
+
+    for (index, feature) in record.features.iter().enumerate() {
+        let parts = parse_location(&feature.location)
+            .with_context(|| format!("Feature {} in record {}", feature.key, record.seq_id))?;
+        let mut attributes = qualifiers_to_attributes(&feature.qualifiers);
+        let locus_tag = attributes.get("locus_tag").cloned();
+
+        let mut id = match &locus_tag {
+            Some(locus_tag) => format!("{}-{}", feature.key, locus_tag),
+            None => format!("{}-{}-{}", feature.key, record.seq_id, index + 1),
+        };
+        // e.g. a gene with multiple CDS, the first one keeps the ID
+        let mut count = 1;
+        while ids.contains(&id) {
+            count += 1;
+            id = match &locus_tag {
+                Some(locus_tag) => format!("{}-{}-{}", feature.key, locus_tag, count),
+                None => format!("{}-{}-{}-{}", feature.key, record.seq_id, index + 1, count),
+            };
+        }
+        ids.insert(id.clone());
+        attributes.insert("ID".into(), id);
+        if let Some(locus_tag) = locus_tag {
+            if feature.key == "gene" {
+                genes.insert(locus_tag);
+            } else if genes.contains(&locus_tag) {
+                attributes.insert("Parent".into(), format!("gene-{}", locus_tag));
+            }
+        }
+
+        let feature_type = match feature.key.as_str() {
+            "source" => "region",
+            key => key,
+        };
+        // phase of the first part, from /codon_start
+        let mut offset: u64 = match attributes.remove("codon_start") {
+            Some(value) => value.parse::<u64>().unwrap_or(1).saturating_sub(1),
+            None => 0,
+        };
+
+        let partial = parts
+            .iter()
+            .any(|part| part.partial_start || part.partial_end);
+        for part in parts {
+            // positions can be in reverse order, e.g. a site across the
+            // origin of a circular sequence (`4641652^1`)
+            let start = part.start.min(part.end);
+            let end = part.start.max(part.end);
+            let phase = if feature.key == "CDS" {
+                let phase = offset.to_string();
+                let length = end - start + 1;
+                // bases of the last codon of this part, left for the next one
+                offset = (3 - (length + 3 - offset) % 3) % 3;
+                phase
+            } else {
+                ".".to_string()
+            };
+            let mut attributes = attributes.clone();
+            if partial {
+                attributes.insert("partial".into(), "true".into());
+            }
+            if part.partial_start {
+                attributes.insert("start_range".into(), format!(".,{}", start));
+            }
+            if part.partial_end {
+                attributes.insert("end_range".into(), format!("{},.", end));
+            }
+            annotations.push(Annotation {
+                seq_id: record.seq_id.clone(),
+                source: source.to_string(),
+                feature_type: feature_type.to_string(),
+                start,
+                end,
+                score: 0.,
+                strand: Strand::from_value(if part.minus { "-" } else { "+" }),
+                phase: Phase::from_value(&phase).context("Cannot parse Phase")?,
+                uid: Uuid::new_v4(),
+                attributes,
+                taxon_id: 0,
+            });
+        }
+    }
+
+    Ok(annotations)
+}
+
+/// Converts the attributes of an annotation to qualifiers, sorted by name.
+/// Values are decoded and multi-value attributes split in one qualifier
+/// per value.
+fn attributes_to_qualifiers(attributes: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut qualifiers: Vec<(String, String)> = Vec::new();
+    for (key, value) in attributes {
+        if SKIPPED_ATTRIBUTES.contains(&key.as_str()) {
+            continue;
+        }
+        let qualifier = match QUALIFIERS_MAP
+            .iter()
+            .find(|(_, attribute)| attribute == key)
+        {
+            Some((qualifier, _)) => qualifier.to_string(),
+            None => key.clone(),
+        };
+        if MULTI_VALUE_ATTRIBUTES.contains(&key.as_str()) {
+            qualifiers.extend(
+                split_attribute_values(value)
+                    .into_iter()
+                    .map(|value| (qualifier.clone(), value)),
+            );
+        } else {
+            qualifiers.push((qualifier, decode_attribute_value(value)));
+        }
+    }
+    qualifiers.sort();
+    qualifiers
+}
+
+/// Groups the annotations of a sequence in features. Annotations with the
+/// same `ID` and feature type are the parts of a single feature, with a
+/// `join()` location. A `region` spanning the whole sequence is used as
+/// the `source` feature, otherwise one is added.
+fn annotations_to_features(annotations: &[Annotation], length: usize) -> Vec<Feature> {
+    let mut groups: Vec<Vec<&Annotation>> = Vec::new();
+    let mut ids: HashMap<(&str, String), usize> = HashMap::new();
+    for annotation in annotations {
+        match annotation.get_attr("ID") {
+            None => groups.push(vec![annotation]),
+            Some(id) => match ids.get(&(annotation.feature_type.as_str(), id.clone())) {
+                Some(index) => groups[*index].push(annotation),
+                None => {
+                    ids.insert((annotation.feature_type.as_str(), id), groups.len());
+                    groups.push(vec![annotation]);
+                }
+            },
+        }
+    }
+
+    let mut features: Vec<(usize, usize, Feature)> = Vec::with_capacity(groups.len());
+    let mut has_source = false;
+    for mut parts in groups {
+        parts.sort_by_key(|annotation| annotation.start);
+        let first = parts[0];
+        let minus = is_minus_strand(first);
+        let start = first.start as usize;
+        let end = parts
+            .iter()
+            .map(|annotation| annotation.end as usize)
+            .max()
+            .unwrap_or(start);
+
+        let mut location = parts
+            .iter()
+            .map(|annotation| {
+                let start_marker = match annotation.attributes.contains_key("start_range") {
+                    true => "<",
+                    false => "",
+                };
+                let end_marker = match annotation.attributes.contains_key("end_range") {
+                    true => ">",
+                    false => "",
+                };
+                match annotation.start == annotation.end {
+                    true => format!("{}{}{}", start_marker, end_marker, annotation.start),
+                    false => format!(
+                        "{}{}..{}{}",
+                        start_marker, annotation.start, end_marker, annotation.end
+                    ),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        if parts.len() > 1 {
+            location = format!("join({})", location);
+        }
+        if minus {
+            location = format!("complement({})", location);
+        }
+
+        let mut qualifiers = attributes_to_qualifiers(&first.attributes);
+        if first.feature_type == "CDS" {
+            // the phase of the part at the 5' end
+            let five_prime = if minus { parts[parts.len() - 1] } else { first };
+            if let Ok(phase) = five_prime.phase.to_string().parse::<usize>() {
+                if phase > 0 {
+                    qualifiers.retain(|(key, _)| key != "codon_start");
+                    qualifiers.insert(0, ("codon_start".into(), (phase + 1).to_string()));
+                }
+            }
+        }
+
+        let key = if first.feature_type == "region" && start == 1 && end == length && !has_source {
+            has_source = true;
+            "source".to_string()
+        } else {
+            first.feature_type.clone()
+        };
+        features.push((
+            start,
+            end,
+            Feature {
+                key,
+                location,
+                qualifiers,
+            },
+        ));
+    }
+
+    if !has_source {
+        features.push((
+            1,
+            length,
+            Feature {
+                key: "source".into(),
+                location: format!("1..{}", length),
+                qualifiers: vec![("mol_type".into(), "genomic DNA".into())],
+            },
+        ));
+    }
+    // by start, longer features first, with `source` at the top
+    features.sort_by_key(|(start, end, feature)| {
+        (feature.key != "source", *start, std::cmp::Reverse(*end))
+    });
+    features
+        .into_iter()
+        .map(|(_, _, feature)| feature)
+        .collect()
+}
+
+/// Splits a text in lines of at most `width` characters, breaking after
+/// one of the `breaks` characters if possible. Spaces at the breaks are
+/// removed.
+fn wrap_text(text: &str, width: usize, breaks: &[char]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut chars: &[char] = &text.chars().collect::<Vec<char>>();
+    while chars.len() > width {
+        let position = match chars[..=width].iter().rposition(|c| breaks.contains(c)) {
+            Some(position) if position > 0 && chars[position] == ' ' => position,
+            Some(position) if position > 0 && position < width => position + 1,
+            _ => width,
+        };
+        lines.push(chars[..position].iter().collect());
+        chars = &chars[position..];
+        if chars.first() == Some(&' ') {
+            chars = &chars[1..];
+        }
+    }
+    lines.push(chars.iter().collect());
+    lines
+}
+
+/// Writes a feature of the feature table, with the prefix used by GenBank
+/// (spaces) or EMBL (`FT`)
+fn write_feature(writer: &mut dyn Write, prefix: &str, feature: &Feature) -> Result<()> {
+    let indent = format!("{}{}", prefix, " ".repeat(16));
+    for (index, line) in wrap_text(&feature.location, FEATURE_WIDTH, &[','])
+        .iter()
+        .enumerate()
+    {
+        match index {
+            0 => writeln!(writer, "{}{:<15} {}", prefix, feature.key, line)?,
+            _ => writeln!(writer, "{}{}", indent, line)?,
+        }
+    }
+    for (key, value) in &feature.qualifiers {
+        let text = if FLAG_QUALIFIERS.contains(&key.as_str()) && value == "true" {
+            format!("/{}", key)
+        } else if NUMERIC_QUALIFIERS.contains(&key.as_str()) {
+            format!("/{}={}", key, value)
+        } else {
+            format!("/{}=\"{}\"", key, value.replace('"', "\"\""))
+        };
+        for line in wrap_text(&text, FEATURE_WIDTH, &[' ']) {
+            writeln!(writer, "{}{}", indent, line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a record in GenBank format. The date in the LOCUS line is a
+/// placeholder.
+fn write_genbank_record(
+    writer: &mut dyn Write,
+    seq_id: &str,
+    sequence: &[u8],
+    features: &[Feature],
+) -> Result<()> {
+    writeln!(
+        writer,
+        "LOCUS       {:<16} {:>11} bp    DNA     linear   UNK 01-JAN-1980",
+        seq_id,
+        sequence.len()
+    )?;
+    writeln!(writer, "DEFINITION  .")?;
+    writeln!(writer, "ACCESSION   {}", seq_id)?;
+    writeln!(writer, "VERSION     {}", seq_id)?;
+    writeln!(writer, "KEYWORDS    .")?;
+    writeln!(writer, "SOURCE      .")?;
+    writeln!(writer, "  ORGANISM  .")?;
+    writeln!(writer, "FEATURES             Location/Qualifiers")?;
+    for feature in features {
+        write_feature(writer, "     ", feature)?;
+    }
+    writeln!(writer, "ORIGIN")?;
+    for (index, chunk) in sequence.chunks(60).enumerate() {
+        let blocks: Vec<String> = chunk
+            .chunks(10)
+            .map(|block| String::from_utf8_lossy(block).to_lowercase())
+            .collect();
+        writeln!(writer, "{:>9} {}", index * 60 + 1, blocks.join(" "))?;
+    }
+    writeln!(writer, "//")?;
+    Ok(())
+}
+
+/// Writes a record in EMBL format
+fn write_embl_record(
+    writer: &mut dyn Write,
+    seq_id: &str,
+    sequence: &[u8],
+    features: &[Feature],
+) -> Result<()> {
+    writeln!(
+        writer,
+        "ID   {}; SV 1; linear; genomic DNA; STD; UNC; {} BP.",
+        seq_id,
+        sequence.len()
+    )?;
+    writeln!(writer, "XX")?;
+    writeln!(writer, "AC   {};", seq_id)?;
+    writeln!(writer, "XX")?;
+    writeln!(writer, "DE   .")?;
+    writeln!(writer, "XX")?;
+    writeln!(writer, "FH   Key             Location/Qualifiers")?;
+    writeln!(writer, "FH")?;
+    for feature in features {
+        write_feature(writer, "FT   ", feature)?;
+    }
+    writeln!(writer, "XX")?;
+    let mut counts = [0; 5];
+    for base in sequence {
+        match base.to_ascii_lowercase() {
+            b'a' => counts[0] += 1,
+            b'c' => counts[1] += 1,
+            b'g' => counts[2] += 1,
+            b't' => counts[3] += 1,
+            _ => counts[4] += 1,
+        }
+    }
+    writeln!(
+        writer,
+        "SQ   Sequence {} BP; {} A; {} C; {} G; {} T; {} other;",
+        sequence.len(),
+        counts[0],
+        counts[1],
+        counts[2],
+        counts[3],
+        counts[4]
+    )?;
+    for (index, chunk) in sequence.chunks(60).enumerate() {
+        let blocks: Vec<String> = chunk
+            .chunks(10)
+            .map(|block| String::from_utf8_lossy(block).to_lowercase())
+            .collect();
+        writeln!(
+            writer,
+            "     {:<65}{:>10}",
+            blocks.join(" "),
+            index * 60 + chunk.len()
+        )?;
+    }
+    writeln!(writer, "//")?;
+    Ok(())
+}
+
+/// Writes the annotations and sequences as GenBank or EMBL records, one per
+/// sequence. Records are in the order the sequences are found in the
+/// annotations, followed by the sequences without annotations.
+fn gff_to_genbank(
+    annotations: Vec<Annotation>,
+    sequences: &Sequences,
+    output_file: &mut dyn Write,
+    embl: bool,
+) -> Result<()> {
+    let mut seq_ids: Vec<String> = Vec::new();
+    let mut records: HashMap<String, Vec<Annotation>> = HashMap::new();
+    for annotation in annotations {
+        if !records.contains_key(&annotation.seq_id) {
+            seq_ids.push(annotation.seq_id.clone());
+        }
+        records
+            .entry(annotation.seq_id.clone())
+            .or_default()
+            .push(annotation);
+    }
+    let mut without_annotations: Vec<&String> = sequences
+        .keys()
+        .filter(|seq_id| !records.contains_key(*seq_id))
+        .collect();
+    without_annotations.sort();
+    seq_ids.extend(without_annotations.into_iter().cloned());
+
+    for seq_id in seq_ids {
+        let sequence = match sequences.get(&seq_id) {
+            None => bail!("Sequence not found for {}", seq_id),
+            Some(sequence) => sequence,
+        };
+        let annotations = records.remove(&seq_id).unwrap_or_default();
+        let features = annotations_to_features(&annotations, sequence.len());
+        if embl {
+            write_embl_record(output_file, &seq_id, sequence, &features)?;
+        } else {
+            write_genbank_record(output_file, &seq_id, sequence, &features)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn genbank_command(options: GenbankCommand) -> Result<()> {
+    let input_file = file_or_stdin(&options.input_file)?;
+    let mut output_file = file_or_stdout(&options.output_file)?;
+
+    if options.reverse {
+        let gff_file = GffFile::from_reader(input_file);
+        let (annotations, sequences) = match &options.fasta_file {
+            Some(fasta_file) => {
+                info!("Reading sequences from file {}", fasta_file.display());
+                let sequences = read_fasta(BufReader::new(open_file_base(fasta_file)?))?;
+                (gff_file.annotations().collect(), sequences)
+            }
+            None => {
+                info!("Using sequences in the ##FASTA section");
+                let annotations: Vec<Annotation> = gff_file.annotations().collect();
+                (annotations, gff_file.read_sequences()?)
+            }
+        };
+        info!("Read {} annotations", annotations.len());
+        return gff_to_genbank(annotations, &sequences, &mut output_file, options.embl);
+    }
+
+    if let Some(path) = &options.input_file {
+        info!("Reading GenBank from file {}", path.display());
+    }
+
+    writeln!(output_file, "##gff-version 3")?;
+
+    let mut sequences: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut count = 0;
+    for record in GenbankReader::from_reader(input_file) {
+        let record = record?;
+        writeln!(
+            output_file,
+            "##sequence-region {} 1 {}",
+            record.seq_id,
+            record.sequence.len()
+        )?;
+        for annotation in record_to_annotations(&record, &options.source)? {
+            writeln!(output_file, "{}", annotation)?;
+            count += 1;
+        }
+        if options.fasta {
+            sequences.push((record.seq_id, record.sequence));
+        }
+    }
+    info!("Converted {} annotations", count);
+
+    if options.fasta {
+        writeln!(output_file, "##FASTA")?;
+        for (seq_id, sequence) in sequences {
+            write_fasta(
+                &mut output_file,
+                &seq_id,
+                &sequence.to_ascii_uppercase(),
+                60,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qualifiers(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn qualifier_values_round_trip() {
+        let original = qualifiers(&[
+            ("db_xref", "GI:1,2"),
+            ("db_xref", "UniProt:P1"),
+            ("note", "a; b=c"),
+            ("product", "x; y=z, 100%"),
+            ("pseudo", ""),
+        ]);
+        let attributes = qualifiers_to_attributes(&original);
+        assert_eq!(attributes["Dbxref"], "GI:1%2C2,UniProt:P1");
+        assert_eq!(attributes["product"], "x%3B y%3Dz%2C 100%25");
+        assert_eq!(attributes["pseudo"], "true");
+
+        let expected = qualifiers(&[
+            ("db_xref", "GI:1,2"),
+            ("db_xref", "UniProt:P1"),
+            ("note", "a; b=c"),
+            ("product", "x; y=z, 100%"),
+            ("pseudo", "true"),
+        ]);
+        assert_eq!(attributes_to_qualifiers(&attributes), expected);
+    }
+
+    fn part(start: u64, end: u64, minus: bool) -> LocationPart {
+        LocationPart {
+            start,
+            end,
+            minus,
+            partial_start: false,
+            partial_end: false,
+        }
+    }
+
+    fn partial(
+        start: u64,
+        end: u64,
+        minus: bool,
+        partial_start: bool,
+        partial_end: bool,
+    ) -> LocationPart {
+        LocationPart {
+            start,
+            end,
+            minus,
+            partial_start,
+            partial_end,
+        }
+    }
+
+    #[test]
+    fn locations() {
+        let cases = vec![
+            ("10..60", vec![part(10, 60, false)]),
+            ("100", vec![part(100, 100, false)]),
+            ("complement(10..60)", vec![part(10, 60, true)]),
+            (
+                "join(1..20,30..45)",
+                vec![part(1, 20, false), part(30, 45, false)],
+            ),
+            (
+                "complement(join(10..30,40..60))",
+                vec![part(40, 60, true), part(10, 30, true)],
+            ),
+            (
+                "join(complement(40..60),complement(10..30))",
+                vec![part(40, 60, true), part(10, 30, true)],
+            ),
+            (
+                "order(5..10,complement(20..30))",
+                vec![part(5, 10, false), part(20, 30, true)],
+            ),
+            ("<10..>60", vec![partial(10, 60, false, true, true)]),
+            (
+                "complement(<10..>60)",
+                vec![partial(10, 60, true, true, true)],
+            ),
+            (
+                "complement(join(<10..30,40..60))",
+                vec![part(40, 60, true), partial(10, 30, true, true, false)],
+            ),
+            (
+                "join(1..20,30..>45)",
+                vec![part(1, 20, false), partial(30, 45, false, false, true)],
+            ),
+            ("3^4", vec![part(3, 4, false)]),
+            ("complement(3^4)", vec![part(3, 4, true)]),
+            (
+                "join(10..20,AB000001.1:1..50,30..40)",
+                vec![part(10, 20, false), part(30, 40, false)],
+            ),
+            (
+                "join(1..10,\n20..30)",
+                vec![part(1, 10, false), part(20, 30, false)],
+            ),
+        ];
+        for (location, expected) in cases {
+            assert_eq!(parse_location(location).unwrap(), expected, "{}", location);
+        }
+        assert!(parse_location("10..abc").is_err());
+    }
+
+    fn record(location: &str, qualifiers: Vec<(String, String)>) -> Record {
+        Record {
+            seq_id: "seq".into(),
+            features: vec![Feature {
+                key: "CDS".into(),
+                location: location.into(),
+                qualifiers,
+            }],
+            sequence: b"A".repeat(200),
+        }
+    }
+
+    /// Location, codon_start and (start, phase) of the parts in 5' to 3'
+    /// order
+    type PhaseCase = (&'static str, &'static str, Vec<(u64, &'static str)>);
+
+    #[test]
+    fn cds_phases() {
+        let cases: Vec<PhaseCase> = vec![
+            ("1..30", "1", vec![(1, "0")]),
+            ("1..30", "3", vec![(1, "2")]),
+            ("join(1..10,20..30)", "1", vec![(1, "0"), (20, "2")]),
+            ("join(1..11,20..30)", "2", vec![(1, "1"), (20, "2")]),
+            (
+                "join(1..12,20..30,40..50)",
+                "1",
+                vec![(1, "0"), (20, "0"), (40, "1")],
+            ),
+            ("complement(10..60)", "2", vec![(10, "1")]),
+            ("60..10", "1", vec![(10, "0")]),
+            ("100^1", "1", vec![(1, "0")]),
+            (
+                "complement(join(10..30,40..60))",
+                "2",
+                vec![(40, "1"), (10, "1")],
+            ),
+            (
+                "complement(join(10..30,40..61))",
+                "1",
+                vec![(40, "0"), (10, "2")],
+            ),
+            (
+                "complement(join(10..30,40..50,60..70))",
+                "3",
+                vec![(60, "2"), (40, "0"), (10, "1")],
+            ),
+        ];
+        for (location, codon_start, expected) in cases {
+            let record = record(location, qualifiers(&[("codon_start", codon_start)]));
+            let annotations = record_to_annotations(&record, "test").unwrap();
+            let phases: Vec<(u64, String)> = annotations
+                .iter()
+                .map(|annotation| (annotation.start, annotation.phase.to_string()))
+                .collect();
+            let expected: Vec<(u64, String)> = expected
+                .into_iter()
+                .map(|(start, phase)| (start, phase.to_string()))
+                .collect();
+            assert_eq!(phases, expected, "{} codon_start={}", location, codon_start);
+            assert!(annotations
+                .iter()
+                .all(|annotation| !annotation.attributes.contains_key("codon_start")));
+        }
+    }
+
+    #[test]
+    fn locations_round_trip() {
+        for location in [
+            "complement(<10..>60)",
+            "complement(join(<10..30,40..60))",
+            "join(<1..20,30..>45)",
+            "complement(join(10..30,40..60))",
+            "100",
+        ] {
+            let annotations = record_to_annotations(&record(location, Vec::new()), "test").unwrap();
+            let features = annotations_to_features(&annotations, 200);
+            let feature = features
+                .iter()
+                .find(|feature| feature.key == "CDS")
+                .unwrap();
+            assert_eq!(feature.location, location);
+            assert!(feature
+                .qualifiers
+                .iter()
+                .all(|(key, _)| !matches!(key.as_str(), "partial" | "start_range" | "end_range")));
+        }
+        let annotations =
+            record_to_annotations(&record("complement(<10..>60)", Vec::new()), "test").unwrap();
+        assert_eq!(annotations[0].attributes["partial"], "true");
+        assert_eq!(annotations[0].attributes["start_range"], ".,10");
+        assert_eq!(annotations[0].attributes["end_range"], "60,.");
+    }
+
+    #[test]
+    fn codon_start_round_trip() {
+        let location = "complement(join(10..30,40..60))";
+        let annotations = record_to_annotations(
+            &record(location, qualifiers(&[("codon_start", "2")])),
+            "test",
+        )
+        .unwrap();
+        let features = annotations_to_features(&annotations, 200);
+        let feature = features
+            .iter()
+            .find(|feature| feature.key == "CDS")
+            .unwrap();
+        assert_eq!(
+            feature.qualifiers[0],
+            ("codon_start".to_string(), "2".to_string())
+        );
+    }
+
+    #[test]
+    fn unique_ids() {
+        let feature = |key: &str, location: &str| Feature {
+            key: key.into(),
+            location: location.into(),
+            qualifiers: qualifiers(&[("locus_tag", "ABC_001")]),
+        };
+        let record = Record {
+            seq_id: "seq".into(),
+            features: vec![
+                feature("gene", "1..90"),
+                feature("CDS", "join(1..30,41..90)"),
+                feature("CDS", "1..90"),
+                feature("CDS", "1..60"),
+            ],
+            sequence: b"A".repeat(200),
+        };
+        let annotations = record_to_annotations(&record, "test").unwrap();
+        let ids: Vec<String> = annotations
+            .iter()
+            .map(|annotation| annotation.attributes["ID"].clone())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                "gene-ABC_001",
+                "CDS-ABC_001",
+                "CDS-ABC_001",
+                "CDS-ABC_001-2",
+                "CDS-ABC_001-3"
+            ]
+        );
+        assert!(annotations[1..]
+            .iter()
+            .all(|annotation| annotation.attributes["Parent"] == "gene-ABC_001"));
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::{bail, Result};
+
+/// NCBI translation tables, as amino acids and start codons for each codon
+/// in the `TCAG` order used by NCBI (TTT, TTC, TTA, TTG, TCT, ...)
+const TABLES: [(u8, &str, &str); 26] = [
+    (
+        1,
+        "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**--*----M---------------M----------------------------",
+    ),
+    (
+        2,
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        "----------**--------------------MMMM----------**---M------------",
+    ),
+    (
+        3,
+        "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**----------------------MM---------------M------------",
+    ),
+    (
+        4,
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--MM------**-------M------------MMMM---------------M------------",
+    ),
+    (
+        5,
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        "---M------**--------------------MMMM---------------M------------",
+    ),
+    (
+        6,
+        "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        9,
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "----------**-----------------------M---------------M------------",
+    ),
+    (
+        10,
+        "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**-----------------------M----------------------------",
+    ),
+    (
+        11,
+        "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**--*----M------------MMMM---------------M------------",
+    ),
+    (
+        12,
+        "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "-------------------M---------------M----------------------------",
+    ),
+    (
+        13,
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        "---M------**----------------------MM---------------M------------",
+    ),
+    (
+        14,
+        "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "-----------*-----------------------M----------------------------",
+    ),
+    (
+        16,
+        "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------*---*--------------------M----------------------------",
+    ),
+    (
+        21,
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "----------**-----------------------M---------------M------------",
+    ),
+    (
+        22,
+        "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "------*---*---*--------------------M----------------------------",
+    ),
+    (
+        23,
+        "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--*-------**--*-----------------M--M---------------M------------",
+    ),
+    (
+        24,
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        "---M------**-------M---------------M---------------M------------",
+    ),
+    (
+        25,
+        "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**-----------------------M---------------M------------",
+    ),
+    (
+        26,
+        "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "-------------------M---------------M----------------------------",
+    ),
+    (
+        27,
+        "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        28,
+        "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**--*--------------------M----------------------------",
+    ),
+    (
+        29,
+        "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        30,
+        "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------",
+    ),
+    (
+        31,
+        "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**-----------------------M----------------------------",
+    ),
+    (
+        32,
+        "FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------*---*----M------------MMMM---------------M------------",
+    ),
+    (
+        33,
+        "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        "---M-------*-------M---------------M---------------M------------",
+    ),
+];
+
+/// Genetic code used to translate nucleotide sequences
+#[derive(Clone, Copy)]
+pub struct GeneticCode {
+    amino_acids: &'static [u8],
+    starts: &'static [u8],
+}
+
+/// Index of a base in the `TCAG` order, `None` for other characters
+fn base_index(base: u8) -> Option<usize> {
+    match base {
+        b'T' | b't' | b'U' | b'u' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'A' | b'a' => Some(2),
+        b'G' | b'g' => Some(3),
+        _ => None,
+    }
+}
+
+impl GeneticCode {
+    /// Returns the genetic code for a NCBI translation table number
+    pub fn from_table(table: u8) -> Result<Self> {
+        match TABLES.iter().find(|(number, _, _)| *number == table) {
+            None => bail!(
+                "Unknown translation table: {}, the supported ones are: {}",
+                table,
+                TABLES
+                    .iter()
+                    .map(|(number, _, _)| number.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Some((_, amino_acids, starts)) => Ok(GeneticCode {
+                amino_acids: amino_acids.as_bytes(),
+                starts: starts.as_bytes(),
+            }),
+        }
+    }
+
+    fn codon_index(codon: &[u8]) -> Option<usize> {
+        Some(base_index(codon[0])? * 16 + base_index(codon[1])? * 4 + base_index(codon[2])?)
+    }
+
+    /// Translates a nucleotide sequence, ignoring incomplete codons at the
+    /// end. If `start` is true and the first codon is a start codon for
+    /// this code, it's translated as `M`. Codons with ambiguous bases are
+    /// translated as `X`.
+    pub fn translate(&self, sequence: &[u8], start: bool) -> Vec<u8> {
+        sequence
+            .chunks_exact(3)
+            .enumerate()
+            .map(|(position, codon)| match GeneticCode::codon_index(codon) {
+                None => b'X',
+                Some(index) if position == 0 && start && self.starts[index] == b'M' => b'M',
+                Some(index) => self.amino_acids[index],
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_have_all_codons() {
+        for (number, amino_acids, starts) in TABLES {
+            assert_eq!(amino_acids.len(), 64, "table {}", number);
+            assert_eq!(starts.len(), 64, "table {}", number);
+        }
+    }
+
+    #[test]
+    fn start_codons() {
+        let standard = GeneticCode::from_table(1).unwrap();
+        let bacterial = GeneticCode::from_table(11).unwrap();
+        for (sequence, expected_standard, expected_bacterial) in [
+            ("ATGAAA", "MK", "MK"),
+            ("TTGAAA", "MK", "MK"),
+            ("GTGAAA", "VK", "MK"),
+            ("ATTAAA", "IK", "MK"),
+        ] {
+            assert_eq!(
+                standard.translate(sequence.as_bytes(), true),
+                expected_standard.as_bytes()
+            );
+            assert_eq!(
+                bacterial.translate(sequence.as_bytes(), true),
+                expected_bacterial.as_bytes()
+            );
+        }
+        // start codons are only translated as `M` at the start
+        assert_eq!(bacterial.translate(b"ATGGTG", true), b"MV");
+        assert_eq!(bacterial.translate(b"GTGAAA", false), b"VK");
+    }
+
+    #[test]
+    fn reassigned_codons() {
+        let standard = GeneticCode::from_table(1).unwrap();
+        let mycoplasma = GeneticCode::from_table(4).unwrap();
+        assert_eq!(standard.translate(b"TGGTGATAA", false), b"W**");
+        assert_eq!(mycoplasma.translate(b"TGGTGATAA", false), b"WW*");
+    }
+
+    #[test]
+    fn unknown_table() {
+        let error = GeneticCode::from_table(7).err().unwrap().to_string();
+        assert!(error.contains("Unknown translation table: 7"));
+        assert!(error.contains("1, 2, 3"));
+    }
+}
@@ -0,0 +1,330 @@
+use super::cli::GffOutputOptions;
+use super::fasta::{read_fasta, SequenceLengths, Sequences};
+use super::utils::file_or_stdout;
+use anyhow::Result;
+use bio_rascal::gff::{Annotation, GffReader};
+use log::info;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// State shared by `GffFile` and the reader passed to the annotation parser
+struct Sections {
+    reader: BufReader<Box<dyn Read>>,
+    /// Annotation line being passed to the parser
+    line: Vec<u8>,
+    /// Bytes of `line` already passed to the parser
+    position: usize,
+    /// First line of the FASTA section, either `##FASTA` or a sequence header
+    fasta_start: Option<Vec<u8>>,
+    /// Comments and directives, with the number of annotation lines before
+    /// each of them
+    comments: VecDeque<(usize, Vec<u8>)>,
+    /// Number of annotation lines passed to the parser
+    lines_read: usize,
+    /// Number of lines read from the input, including comments and empty
+    /// lines
+    line_number: usize,
+    /// Number of annotations returned by the parser
+    annotations_read: usize,
+}
+
+impl Sections {
+    /// Passes the annotation lines, stopping at the start of the FASTA
+    /// section, which is kept in the underlying reader. Comments and
+    /// directives are kept aside, empty lines skipped.
+    fn read_annotations(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.line.len() {
+            if self.fasta_start.is_some() {
+                return Ok(0);
+            }
+            self.line.clear();
+            self.position = 0;
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(0);
+            }
+            self.line_number += 1;
+            if self.line.starts_with(b"##FASTA") || self.line.starts_with(b">") {
+                self.fasta_start = Some(std::mem::take(&mut self.line));
+                return Ok(0);
+            }
+            if self.line.starts_with(b"#") {
+                let line = std::mem::take(&mut self.line);
+                self.comments.push_back((self.lines_read, line));
+            } else if self.line.iter().all(u8::is_ascii_whitespace) {
+                self.line.clear();
+            } else {
+                self.lines_read += 1;
+            }
+        }
+        let size = buf.len().min(self.line.len() - self.position);
+        buf[..size].copy_from_slice(&self.line[self.position..self.position + size]);
+        self.position += size;
+        Ok(size)
+    }
+}
+
+/// Reader that only passes the annotation lines of a GFF file
+struct AnnotationReader {
+    sections: Rc<RefCell<Sections>>,
+}
+
+impl Read for AnnotationReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.sections.borrow_mut().read_annotations(buf)
+    }
+}
+
+/// Iterator over the annotations of a `GffFile`, keeping track of how many
+/// were read, to write the comments in the right place
+pub struct Annotations<I> {
+    iter: I,
+    sections: Rc<RefCell<Sections>>,
+}
+
+impl<I: Iterator> Iterator for Annotations<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        self.sections.borrow_mut().annotations_read += 1;
+        Some(item)
+    }
+}
+
+/// A GFF file split in comments, annotations and `##FASTA` section
+///
+/// The annotations are read with `annotations` (or `reader` and `track` for
+/// other parsers, like the GTF one) and, once they are all read, the FASTA
+/// section can be written with `write_fasta`. Comments and directives are
+/// written by `GffWriter` in the same position they had in the input.
+#[derive(Clone)]
+pub struct GffFile {
+    sections: Rc<RefCell<Sections>>,
+}
+
+impl GffFile {
+    pub fn from_reader(reader: Box<dyn Read>) -> Self {
+        GffFile {
+            sections: Rc::new(RefCell::new(Sections {
+                reader: BufReader::new(reader),
+                line: Vec::new(),
+                position: 0,
+                fasta_start: None,
+                comments: VecDeque::new(),
+                lines_read: 0,
+                line_number: 0,
+                annotations_read: 0,
+            })),
+        }
+    }
+
+    /// Returns a reader with only the annotation lines
+    pub fn reader(&self) -> Box<dyn Read> {
+        Box::new(AnnotationReader {
+            sections: self.sections.clone(),
+        })
+    }
+
+    /// Returns the number of lines read from the input, comments and empty
+    /// lines included. Once a line is returned by `reader`, it's its line
+    /// number in the input.
+    pub fn line_number(&self) -> usize {
+        self.sections.borrow().line_number
+    }
+
+    /// Returns a `GffReader` over the annotations
+    pub fn annotations(&self) -> Annotations<GffReader> {
+        self.track(GffReader::from_reader(self.reader()))
+    }
+
+    /// Wraps an iterator over the lines returned by `reader`, one item per
+    /// line, so the position of comments is kept
+    pub fn track<I: Iterator>(&self, iter: I) -> Annotations<I> {
+        Annotations {
+            iter,
+            sections: self.sections.clone(),
+        }
+    }
+
+    /// Writes the FASTA section, if present, to `writer`, returning the
+    /// number of bytes written. If `directive` is true, the `##FASTA` line
+    /// is written before the sequences, otherwise it's skipped. It must be
+    /// called after all annotations are read.
+    pub fn write_fasta(&self, writer: &mut dyn Write, directive: bool) -> Result<u64> {
+        let mut sections = self.sections.borrow_mut();
+        let first_line = match sections.fasta_start.take() {
+            None => return Ok(0),
+            Some(first_line) => first_line,
+        };
+        if directive {
+            writer.write_all(b"##FASTA\n")?;
+        }
+        if !first_line.starts_with(b"##FASTA") {
+            writer.write_all(&first_line)?;
+        }
+        let size = std::io::copy(&mut sections.reader, writer)?;
+        Ok(size + first_line.len() as u64)
+    }
+
+    /// Returns the sequence lengths in the `##sequence-region` directives
+    /// not yet written. It should be called after all annotations are read.
+    pub fn sequence_regions(&self) -> SequenceLengths {
+        let sections = self.sections.borrow();
+        sections
+            .comments
+            .iter()
+            .filter_map(|(_, line)| {
+                let line = String::from_utf8_lossy(line);
+                let fields: Vec<&str> = line
+                    .strip_prefix("##sequence-region")?
+                    .split_whitespace()
+                    .collect();
+                match fields.as_slice() {
+                    [seq_id, _, end] => Some((seq_id.to_string(), end.parse().ok()?)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Reads the sequences in the FASTA section, if present. It must be
+    /// called after all annotations are read.
+    pub fn read_sequences(&self) -> Result<Sequences> {
+        let mut sections = self.sections.borrow_mut();
+        let first_line = match sections.fasta_start.take() {
+            None => return Ok(Sequences::new()),
+            Some(first_line) if first_line.starts_with(b"##FASTA") => Vec::new(),
+            Some(first_line) => first_line,
+        };
+        read_fasta(BufReader::new(Cursor::new(first_line).chain(&mut sections.reader)))
+    }
+}
+
+/// Returns `##gff-version 3` in place of a `##gff-version` directive with
+/// another version (e.g. of a GTF or GFF2 input), since the output is GFF3.
+/// Other lines are returned unchanged.
+fn gff3_version(line: Vec<u8>) -> Vec<u8> {
+    match line.strip_prefix(b"##gff-version") {
+        Some(version) if version.iter().find(|c| !c.is_ascii_whitespace()) != Some(&b'3') => {
+            b"##gff-version 3\n".to_vec()
+        }
+        _ => line,
+    }
+}
+
+/// Writes annotations to a GFF file, with the comments and directives of
+/// the input and followed by its `##FASTA` section, depending on the
+/// options passed. A `##gff-version 3` directive is added if the input
+/// had none.
+pub struct GffWriter {
+    output: Box<dyn Write>,
+    file: GffFile,
+    strip_fasta: bool,
+    fasta_file: Option<PathBuf>,
+    no_comments: bool,
+    header_written: bool,
+}
+
+impl GffWriter {
+    pub fn new(output: Box<dyn Write>, file: &GffFile, options: &GffOutputOptions) -> Self {
+        GffWriter {
+            output,
+            file: file.clone(),
+            strip_fasta: options.strip_fasta,
+            fasta_file: options.fasta_file.clone(),
+            no_comments: options.no_comments,
+            header_written: false,
+        }
+    }
+
+    /// Writes the comments found before the first `limit` annotations of
+    /// the input, or before the last one read if `limit` is `None`.
+    fn write_comments(&mut self, limit: Option<usize>) -> Result<()> {
+        let mut sections = self.file.sections.borrow_mut();
+
+        if !self.header_written {
+            self.header_written = true;
+            let has_version = sections
+                .comments
+                .iter()
+                .take_while(|(position, _)| *position == 0)
+                .any(|(_, line)| line.starts_with(b"##gff-version"));
+            if !has_version {
+                writeln!(self.output, "##gff-version 3")?;
+            }
+        }
+
+        let limit = limit.unwrap_or(sections.annotations_read);
+        while let Some((position, _)) = sections.comments.front() {
+            if *position >= limit {
+                break;
+            }
+            if let Some((_, line)) = sections.comments.pop_front() {
+                // comments, directives (`##`) and `#!` lines are kept
+                if self.no_comments && !(line.starts_with(b"##") || line.starts_with(b"#!")) {
+                    continue;
+                }
+                let line = gff3_version(line);
+                self.output.write_all(&line)?;
+                if !line.ends_with(b"\n") {
+                    writeln!(self.output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, annotation: &Annotation) -> Result<()> {
+        self.write_comments(None)?;
+        writeln!(self.output, "{}", annotation)?;
+        Ok(())
+    }
+
+    /// Writes the annotation that was at `index` (0-based) in the input,
+    /// after the comments before it. Used when all annotations are read
+    /// before writing them.
+    pub fn write_at(&mut self, annotation: &Annotation, index: usize) -> Result<()> {
+        self.write_comments(Some(index + 1))?;
+        writeln!(self.output, "{}", annotation)?;
+        Ok(())
+    }
+
+    /// Writes the remaining comments and the FASTA section, if not removed,
+    /// either to the output or to a separate file
+    pub fn finish(mut self) -> Result<()> {
+        self.write_comments(Some(usize::MAX))?;
+        if self.strip_fasta {
+            info!("Removing the FASTA section");
+        } else if let Some(path) = &self.fasta_file {
+            let mut fasta_file = file_or_stdout(&Some(path.clone()))?;
+            if self.file.write_fasta(&mut fasta_file, false)? == 0 {
+                info!("No FASTA section found");
+            }
+        } else {
+            self.file.write_fasta(&mut self.output, true)?;
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_directive_is_gff3() {
+        for (line, expected) in [
+            ("##gff-version 2\n", "##gff-version 3\n"),
+            ("##gff-version 2.5\n", "##gff-version 3\n"),
+            ("##gff-version 3\n", "##gff-version 3\n"),
+            ("##gff-version 3.1.26\n", "##gff-version 3.1.26\n"),
+            ("##sequence-region chr1 1 100\n", "##sequence-region chr1 1 100\n"),
+        ] {
+            assert_eq!(gff3_version(line.as_bytes().to_vec()), expected.as_bytes());
+        }
+    }
+}
@@ -1,105 +1,411 @@
-use std::{io::{BufReader, Read, BufRead}, collections::HashMap, str::FromStr, path::{Path, PathBuf}};
-use log::{error, info};
-use super::cli::GtfCommand;
+use std::{io::{BufReader, Read, BufRead, Write}, collections::HashMap, str::FromStr};
+use log::{error, info, warn};
+use super::cli::{ErrorMode, GtfCommand};
+use super::gff::{GffFile, GffWriter};
+use super::utils::{decode_attribute_value, encode_attribute_value, split_attribute_values};
 use uuid::Uuid;
-use anyhow::{Result, Context};
-use bio_rascal::gff::{Phase, Strand, Annotation};
+use anyhow::{bail, Result, Context};
+use bio_rascal::gff::{Phase, Strand, Annotation, GffReader};
+use bio_rascal::taxon::ROOT_TAXON;
 
-fn parse_gtf_attributes(attributes_line: &str) -> (Uuid, HashMap<String, String>) {
+/// Splits the attributes column of a GTF line into key/value pairs, in the
+/// order they are found. Quoted values can contain `;`, spaces and escaped
+/// quotes (`\"`), unquoted values end at the next `;`.
+fn tokenize_gtf_attributes(attributes_line: &str) -> Result<Vec<(String, String)>> {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    let mut chars = attributes_line.chars().peekable();
+
+    loop {
+        // skips separators between attributes
+        while chars.peek().is_some_and(|c| c.is_whitespace() || *c == ';') {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.peek() {
+            if c.is_whitespace() || *c == ';' {
+                break;
+            }
+            key.push(*c);
+            chars.next();
+        }
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+
+        let mut value = String::new();
+        match chars.peek() {
+            Some('"') => {
+                chars.next();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some(next) => value.push(next),
+                            None => break,
+                        },
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        _ => value.push(c),
+                    }
+                }
+                if !closed {
+                    bail!("Unterminated quoted value for attribute {:?}", key);
+                }
+            }
+            Some(';') | None => {
+                error!("Cannot parse attribute: {:?}", key);
+                continue;
+            }
+            Some(_) => {
+                while let Some(c) = chars.peek() {
+                    if *c == ';' {
+                        break;
+                    }
+                    value.push(*c);
+                    chars.next();
+                }
+                value = value.trim_end().to_string();
+            }
+        }
+        pairs.push((key, value));
+    }
+
+    Ok(pairs)
+}
+
+/// Parses the attributes of a GTF line. Keys that are repeated, like `tag`
+/// in GENCODE files, are joined with commas as GFF multi-value attributes.
+/// Values are percent-encoded, since they can contain `;`, `=` or `,`.
+fn parse_gtf_attributes(attributes_line: &str) -> Result<(Uuid, HashMap<String, String>)> {
     let mut uid: Uuid = Uuid::nil();
     let mut attributes: HashMap<String, String> = HashMap::new();
     
-    for field in attributes_line.split(';').map(|f| f.trim()) {
-        if let Some((mut key, mut value)) = field.split_once(' ') {
-            key = key.trim();
-            value = value.trim().trim_matches('"');
-            match key {
-                "uid" => uid = Uuid::from_str(value).context("Cannot convert Uuid").unwrap(),
-                _ => _ = attributes.insert(key.into(), value.into()),
+    for (key, value) in tokenize_gtf_attributes(attributes_line)? {
+        match key.as_str() {
+            "uid" => uid = Uuid::from_str(&value).with_context(|| format!("Cannot convert Uuid: {:?}", value))?,
+            _ => {
+                let value = encode_attribute_value(&value);
+                match attributes.get_mut(&key) {
+                    Some(current) => {
+                        current.push(',');
+                        current.push_str(&value);
+                    }
+                    None => _ = attributes.insert(key, value),
+                }
             }
-        } else {
-            error!("Cannot parse attribute: {:?}", field);
         }
     }
     if uid.is_nil() {
         uid = Uuid::new_v4();
     }
-    (uid, attributes)
+    Ok((uid, attributes))
 }
 
-fn parse_gtf_line(line: &str) -> Annotation {
-    let fields: Vec<&str> = line.trim().trim_end_matches(';').splitn(9, '\t').map(|f| f.trim()).collect();
+fn parse_gtf_line(line: &str) -> Result<Annotation> {
+    let fields: Vec<&str> = line.trim().splitn(9, '\t').map(|f| f.trim()).collect();
+    if fields.len() < 9 {
+        bail!("Expected 9 columns, found {}", fields.len());
+    }
     
-    let (uid, attributes) = parse_gtf_attributes(fields[8]);
+    let (uid, mut attributes) = parse_gtf_attributes(fields[8])?;
+    // taxon_id is part of the structure
+    let taxon_id = match attributes.remove("taxon_id") {
+        Some(value) => value.parse().unwrap_or(0),
+        None => 0,
+    };
     
-    Annotation {
+    Ok(Annotation {
         seq_id: fields[0].to_owned(),
         source: fields[1].to_owned(),
         feature_type: fields[2].to_owned(),
-        start: fields[3].parse().context("Parsing Start field failed").unwrap(),
-        end: fields[4].parse().context("Parsing End field failed").unwrap(),
+        start: fields[3].parse().context("Parsing Start field failed")?,
+        end: fields[4].parse().context("Parsing End field failed")?,
         score: fields[5].parse().unwrap_or(0.),
         strand: Strand::from_value(fields[6]),
-        phase: Phase::from_value(fields[7]).context("Cannot parse Phase").unwrap(),
+        phase: Phase::from_value(fields[7]).context("Cannot parse Phase")?,
         uid,
         attributes,
-        taxon_id: 0,
-    }
+        taxon_id,
+    })
 }
 
+/// Reads the annotation lines of a `GffFile` as GTF. The line numbers in
+/// the errors are the ones in the input, comments and empty lines included
 struct GtfReader {
-    reader: BufReader<Box<dyn Read>>
+    reader: BufReader<Box<dyn Read>>,
+    file: GffFile,
 }
 
 impl GtfReader {
-    pub fn from_reader(reader: Box<dyn Read>) -> Self {
+    pub fn from_file(file: &GffFile) -> Self {
         GtfReader {
-            reader: BufReader::new(reader),
+            reader: BufReader::new(file.reader()),
+            file: file.clone(),
         }
     }
 }
 
 impl Iterator for GtfReader {
-    type Item = Annotation;
+    type Item = Result<Annotation>;
     
     fn next(&mut self) -> Option<Self::Item> {
         let mut buffer = String::new();
         
-        if let Ok(read_size) = self.reader.read_line(&mut buffer) {
-            if read_size == 0 {
-                None
-            } else if buffer.starts_with('#') {
+        loop {
+            buffer.clear();
+            match self.reader.read_line(&mut buffer) {
+                Err(err) => {
+                    let line_number = self.file.line_number();
+                    return Some(Err(err).with_context(|| format!("Cannot read line {}", line_number)))
+                }
+                Ok(0) => return None,
                 // Goes to next iteration
-                self.next()
-            // starts of sequence, stop reading
-            } else if buffer.starts_with('>') {
-                None
-            } else {
-                Some(parse_gtf_line(&buffer))
+                Ok(_) if buffer.starts_with('#') || buffer.trim().is_empty() => continue,
+                Ok(_) => {
+                    let line_number = self.file.line_number();
+                    return Some(parse_gtf_line(&buffer).with_context(|| {
+                        format!("Line {}: {:?}", line_number, buffer.trim_end())
+                    }));
+                }
             }
-        } else {
-            None
         }
     }
 }
 
+/// Features that are not transcripts but part of one, if their parent is a
+/// gene, the gene is used as transcript too
+const TRANSCRIPT_PARTS: [&str; 8] = [
+    "exon",
+    "CDS",
+    "start_codon",
+    "stop_codon",
+    "five_prime_UTR",
+    "three_prime_UTR",
+    "UTR",
+    "intron",
+];
+
+/// Quotes a GTF attribute value, escaping any quote in it
+fn quote_gtf_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+/// Formats an annotation as a GTF line. `gene_id` and `transcript_id` are
+/// written first, followed by `uid`, `taxon_id` (if set) and the other
+/// attributes sorted by key. Multi-value attributes are written as repeated
+/// keys, with the values decoded.
+fn annotation_to_gtf(annotation: &Annotation, gene_id: &str, transcript_id: Option<&str>) -> String {
+    let mut attributes: Vec<String> = vec![format!("gene_id {}", quote_gtf_value(gene_id))];
+    if let Some(transcript_id) = transcript_id {
+        attributes.push(format!("transcript_id {}", quote_gtf_value(transcript_id)));
+    }
+    attributes.push(format!("uid {}", quote_gtf_value(&annotation.uid.to_string())));
+    if annotation.taxon_id >= ROOT_TAXON {
+        attributes.push(format!("taxon_id {}", quote_gtf_value(&annotation.taxon_id.to_string())));
+    }
+    let mut keys: Vec<&String> = annotation
+        .attributes
+        .keys()
+        .filter(|key| !matches!(key.as_str(), "gene_id" | "transcript_id"))
+        .collect();
+    keys.sort();
+    for key in keys {
+        for value in split_attribute_values(&annotation.attributes[key]) {
+            attributes.push(format!("{} {}", key, quote_gtf_value(&value)));
+        }
+    }
+
+    let score = if annotation.score == 0. {
+        ".".to_string()
+    } else {
+        annotation.score.to_string()
+    };
+
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{};",
+        annotation.seq_id,
+        annotation.source,
+        annotation.feature_type,
+        annotation.start,
+        annotation.end,
+        score,
+        annotation.strand,
+        annotation.phase,
+        attributes.join("; ")
+    )
+}
+
+/// Converts a GFF to GTF, deriving `gene_id` and `transcript_id` from the
+/// `ID`/`Parent` hierarchy. Features without a parent are genes, features
+/// whose parent is a gene are transcripts (or parts of one, like CDS in
+/// prokaryotes) and features whose parent is a transcript are parts of it.
+/// Parents are expected to appear before their children.
+fn gff_to_gtf(reader: GffReader, output_file: &mut Box<dyn Write>) -> Result<()> {
+    // ID -> (gene_id, feature has a parent)
+    let mut ids: HashMap<String, (String, bool)> = HashMap::new();
+
+    let mut count = 0;
+    for annotation in reader {
+        let id = annotation
+            .get_attr("ID")
+            .unwrap_or_else(|| annotation.uid.to_string());
+        let parent = annotation
+            .get_attr("Parent")
+            .and_then(|value| value.split(',').next().map(String::from));
+
+        let (gene_id, transcript_id) = match &parent {
+            None => {
+                let transcript_id = match annotation.feature_type.as_str() {
+                    "gene" => None,
+                    _ => Some(id.clone()),
+                };
+                (id.clone(), transcript_id)
+            }
+            Some(parent) => match ids.get(parent) {
+                // the parent is a transcript
+                Some((gene_id, true)) => (gene_id.clone(), Some(parent.clone())),
+                // the parent is a gene or was not found
+                _ => {
+                    if TRANSCRIPT_PARTS.contains(&annotation.feature_type.as_str()) {
+                        (parent.clone(), Some(parent.clone()))
+                    } else {
+                        (parent.clone(), Some(id.clone()))
+                    }
+                }
+            },
+        };
+        // values already in the attributes take precedence
+        let gene_id = annotation
+            .get_attr("gene_id")
+            .map(|value| decode_attribute_value(&value))
+            .unwrap_or(gene_id);
+        let transcript_id = annotation
+            .get_attr("transcript_id")
+            .map(|value| decode_attribute_value(&value))
+            .or(transcript_id);
+
+        writeln!(
+            output_file,
+            "{}",
+            annotation_to_gtf(&annotation, &gene_id, transcript_id.as_deref())
+        )?;
+        ids.insert(id, (gene_id, parent.is_some()));
+        count += 1;
+    }
+
+    info!("Converted {} annotations to GTF", count);
+
+    Ok(())
+}
+
 pub fn gtf_command(options: GtfCommand) -> Result<()> {
     let input_file = super::utils::file_or_stdin(&options.input_file)?;
     let mut output_file = super::utils::file_or_stdout(&options.output_file)?;
     
+    let (input_format, output_format) = if options.reverse {
+        ("GFF", "GTF")
+    } else {
+        ("GTF", "GFF")
+    };
+
     if let Some(path) = options.input_file {
-        info!("Reading GTF from file {}", path.display());
+        info!("Reading {} from file {}", input_format, path.display());
     }
     
     if let Some(path) = options.output_file {
-        info!("Writing GFF to file {}", path.display());
+        info!("Writing {} to file {}", output_format, path.display());
+    }
+
+    if options.reverse {
+        return gff_to_gtf(GffReader::from_reader(input_file), &mut output_file);
     }
     
-    let reader = GtfReader::from_reader(input_file);
+    let gff_file = GffFile::from_reader(input_file);
+    let reader = gff_file.track(GtfReader::from_file(&gff_file));
+    let mut writer = GffWriter::new(output_file, &gff_file, &options.gff_options);
     
+    let mut skipped = 0;
     for annotation in reader {
-        write!(&mut output_file, "{}\n", annotation.to_string())?;
+        let annotation = match annotation {
+            Ok(annotation) => annotation,
+            Err(err) => match options.on_error {
+                ErrorMode::Fail => return Err(err),
+                ErrorMode::Skip => {
+                    skipped += 1;
+                    continue;
+                }
+                ErrorMode::Warn => {
+                    warn!("Skipping line - {:#}", err);
+                    skipped += 1;
+                    continue;
+                }
+            },
+        };
+        writer.write(&annotation)?;
+    }
+
+    if skipped > 0 {
+        warn!("Skipped {} malformed line(s)", skipped);
     }
     
-    Ok(())
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_value_attributes_are_repeated() {
+        let annotation = parse_gtf_line(
+            "chr1\tsrc\texon\t1\t100\t.\t+\t.\tgene_id \"g1\"; transcript_id \"t1\"; tag \"basic\"; tag \"CCDS\";",
+        )
+        .unwrap();
+        assert_eq!(annotation.attributes["tag"], "basic,CCDS");
+
+        let line = annotation_to_gtf(&annotation, "g1", Some("t1"));
+        assert!(line.ends_with("tag \"basic\"; tag \"CCDS\";"));
+    }
+
+    #[test]
+    fn gtf_values_are_encoded() {
+        let annotation = parse_gtf_line(
+            "chr1\tsrc\tgene\t1\t100\t.\t+\t.\tgene_id \"g1\"; note \"a; b=c, d\"; tag \"basic\"; tag \"CCDS\";",
+        )
+        .unwrap();
+        let gff_line = annotation.to_string();
+        assert_eq!(gff_line.split('\t').count(), 9);
+
+        let read_back = GffReader::from_reader(Box::new(std::io::Cursor::new(gff_line)))
+            .next()
+            .unwrap();
+        assert_eq!(read_back.uid, annotation.uid);
+        assert_eq!(decode_attribute_value(&read_back.attributes["note"]), "a; b=c, d");
+        assert_eq!(split_attribute_values(&read_back.attributes["tag"]), vec!["basic", "CCDS"]);
+
+        // and back to GTF
+        let line = annotation_to_gtf(&read_back, "g1", None);
+        assert!(line.contains("note \"a; b=c, d\"; tag \"basic\"; tag \"CCDS\";"));
+    }
+
+    #[test]
+    fn line_numbers_include_comments() {
+        let input = "##gff-version 2.5\n##provider: GENCODE\n#!genome-build GRCh38\n\n\
+            chr1\tsrc\tgene\t1\t100\t.\t+\t.\tgene_id \"g1\";\n\
+            # a comment\n\
+            chr1\tsrc\tgene\tone\t100\t.\t+\t.\tgene_id \"g2\";\n";
+        let gff_file = GffFile::from_reader(Box::new(std::io::Cursor::new(input)));
+        let mut reader = GtfReader::from_file(&gff_file);
+
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().err().unwrap();
+        assert!(format!("{:#}", err).starts_with("Line 7: "));
+        assert!(reader.next().is_none());
+    }
 }
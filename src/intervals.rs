@@ -0,0 +1,143 @@
+use super::bed::{BedFields, BedReader};
+use anyhow::Result;
+use bio_rascal::gff::{Annotation, GffReader};
+use bio_rascal::io::open_file_base;
+use log::info;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Annotations of a sequence, sorted by start, with the maximum end up to
+/// each of them, to stop the search for overlaps early
+pub struct Intervals {
+    annotations: Vec<Annotation>,
+    max_ends: Vec<u64>,
+    /// Positions of the annotations, sorted by end
+    by_end: Vec<usize>,
+}
+
+impl Intervals {
+    fn new(mut annotations: Vec<Annotation>) -> Self {
+        annotations.sort_by_key(|annotation| (annotation.start, annotation.end));
+        let mut max_end = 0;
+        let max_ends = annotations
+            .iter()
+            .map(|annotation| {
+                max_end = max_end.max(annotation.end);
+                max_end
+            })
+            .collect();
+        let mut by_end: Vec<usize> = (0..annotations.len()).collect();
+        by_end.sort_by_key(|index| annotations[*index].end);
+        Intervals {
+            annotations,
+            max_ends,
+            by_end,
+        }
+    }
+
+    /// Returns the annotations starting after `position`, nearest first
+    pub fn right_of(&self, position: u64) -> impl Iterator<Item = &Annotation> {
+        let first = self
+            .annotations
+            .partition_point(|annotation| annotation.start <= position);
+        self.annotations[first..].iter()
+    }
+
+    /// Returns the annotations ending before `position`, nearest first
+    pub fn left_of(&self, position: u64) -> impl Iterator<Item = &Annotation> {
+        let last = self
+            .by_end
+            .partition_point(|index| self.annotations[*index].end < position);
+        self.by_end[..last]
+            .iter()
+            .rev()
+            .map(|index| &self.annotations[*index])
+    }
+
+    /// Returns the annotations overlapping `start` and `end` (1-based,
+    /// inclusive), sorted by start
+    pub fn overlapping(&self, start: u64, end: u64) -> Vec<&Annotation> {
+        let upper = self
+            .annotations
+            .partition_point(|annotation| annotation.start <= end);
+        let mut result: Vec<&Annotation> = Vec::new();
+        for index in (0..upper).rev() {
+            if self.max_ends[index] < start {
+                break;
+            }
+            if self.annotations[index].end >= start {
+                result.push(&self.annotations[index]);
+            }
+        }
+        result.reverse();
+        result
+    }
+}
+
+/// Index of annotations by `seq_id`, to search for overlaps and neighbours
+pub struct IntervalIndex {
+    sequences: HashMap<String, Intervals>,
+}
+
+impl IntervalIndex {
+    pub fn new<I: IntoIterator<Item = Annotation>>(annotations: I) -> Self {
+        let mut groups: HashMap<String, Vec<Annotation>> = HashMap::new();
+        for annotation in annotations {
+            groups
+                .entry(annotation.seq_id.clone())
+                .or_default()
+                .push(annotation);
+        }
+        IntervalIndex {
+            sequences: groups
+                .into_iter()
+                .map(|(seq_id, annotations)| (seq_id, Intervals::new(annotations)))
+                .collect(),
+        }
+    }
+
+    /// Reads the annotations of a GFF or, if `bed` is true, a BED file.
+    /// The BED regions have `region` as feature type and the name as `Name`.
+    pub fn from_file<P: AsRef<Path>>(file_name: P, bed: bool) -> Result<Self> {
+        let file_name = file_name.as_ref();
+        info!("Reading annotations from file {}", file_name.display());
+        let input_file = open_file_base(file_name)?;
+        let annotations: Vec<Annotation> = if bed {
+            let fields = BedFields {
+                source: ".".into(),
+                feature_type: "region".into(),
+                name_attribute: "Name".into(),
+                columns: Vec::new(),
+            };
+            BedReader::from_reader(input_file, fields).collect::<Result<_>>()?
+        } else {
+            GffReader::from_reader(input_file).collect()
+        };
+        info!("Read {} annotations", annotations.len());
+        Ok(IntervalIndex::new(annotations))
+    }
+
+    /// Returns the annotations of a sequence
+    pub fn get(&self, seq_id: &str) -> Option<&Intervals> {
+        self.sequences.get(seq_id)
+    }
+
+    /// Returns the annotations overlapping `start` and `end` (1-based,
+    /// inclusive) on a sequence
+    pub fn overlapping(&self, seq_id: &str, start: u64, end: u64) -> Vec<&Annotation> {
+        match self.sequences.get(seq_id) {
+            None => Vec::new(),
+            Some(intervals) => intervals.overlapping(start, end),
+        }
+    }
+}
+
+/// Returns the number of bases shared by two annotations
+pub fn overlap_length(a: &Annotation, b: &Annotation) -> u64 {
+    let start = a.start.max(b.start);
+    let end = a.end.min(b.end);
+    match end >= start {
+        true => end - start + 1,
+        false => 0,
+    }
+}
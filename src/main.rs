@@ -1,13 +1,37 @@
+mod bed;
 mod cli;
 mod utils;
+mod fasta;
+mod genbank;
+mod genetic_code;
+mod gff;
 mod gtf;
+mod intervals;
+mod taxonomy;
 
 use anyhow::{Ok, Result};
 use clap::{CommandFactory, Parser}; // CommandFactory is necessary for Cli::command()
 use cli::add::add_command;
+use cli::bed::bed_command;
+use cli::blast::blast_command;
+use cli::closest::closest_command;
+use cli::complement::complement_command;
+use cli::eggnog::eggnog_command;
 use cli::fields::fields_command;
+use cli::filter::filter_command;
+use cli::flank::flank_command;
+use cli::getseq::getseq_command;
+use cli::hmmer::hmmer_command;
+use cli::import::import_command;
+use cli::interpro::interpro_command;
+use cli::intersect::intersect_command;
+use cli::json::json_command;
+use cli::merge::merge_command;
 use cli::remove::remove_command;
 use cli::table::table_command;
+use cli::taxon::taxon_command;
+use cli::taxonomy::taxonomy_command;
+use cli::translate::translate_command;
 use cli::view::view_command;
 use cli::*;
 use env_logger::Env;
@@ -31,7 +55,25 @@ fn main() -> Result<()> {
             cli::Commands::Rm(options) => remove_command(&options),
             cli::Commands::View(options) => view_command(&options),
             cli::Commands::Table(options) => table_command(&options),
+            cli::Commands::Eggnog(options) => eggnog_command(&options),
+            cli::Commands::Interpro(options) => interpro_command(&options),
+            cli::Commands::Blast(options) => blast_command(&options),
+            cli::Commands::Hmmer(options) => hmmer_command(&options),
+            cli::Commands::Taxon(options) => taxon_command(&options),
+            cli::Commands::Taxonomy(options) => taxonomy_command(&options),
+            cli::Commands::Intersect(options) => intersect_command(&options),
+            cli::Commands::Closest(options) => closest_command(&options),
+            cli::Commands::Merge(options) => merge_command(&options),
+            cli::Commands::Complement(options) => complement_command(&options),
+            cli::Commands::Flank(options) => flank_command(&options),
             cli::Commands::Gtf(options) => gtf::gtf_command(options),
+            cli::Commands::Genbank(options) => genbank::genbank_command(options),
+            cli::Commands::Filter(options) => filter_command(&options),
+            cli::Commands::Getseq(options) => getseq_command(&options),
+            cli::Commands::Translate(options) => translate_command(&options),
+            cli::Commands::Bed(options) => bed_command(&options),
+            cli::Commands::Json(options) => json_command(&options),
+            cli::Commands::Import(options) => import_command(&options),
             //_ => todo!(),
         };
 
@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use bio_rascal::io::open_file;
+use bio_rascal::taxon::ROOT_TAXON;
+use log::info;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+/// Splits a line of a NCBI taxdump file, where the fields are separated by
+/// `\t|\t` and the line ends with `\t|`
+fn split_dmp_line(line: &str) -> Vec<&str> {
+    line.trim_end_matches(['\t', '|'])
+        .split("\t|\t")
+        .map(|field| field.trim())
+        .collect()
+}
+
+/// NCBI taxonomy, loaded from a local taxdump directory
+pub struct Taxonomy {
+    /// Parent and rank of each taxon
+    nodes: HashMap<u32, (u32, String)>,
+    /// Scientific name of each taxon
+    names: HashMap<u32, String>,
+    /// Taxon IDs merged into another one, from `merged.dmp`
+    merged: HashMap<u32, u32>,
+}
+
+impl Taxonomy {
+    /// Reads `nodes.dmp`, `names.dmp` and, if present, `merged.dmp` from a
+    /// taxdump directory. The files can be compressed.
+    pub fn from_taxdump<P: AsRef<Path>>(directory: P) -> Result<Self> {
+        let directory = directory.as_ref();
+
+        let mut nodes = HashMap::new();
+        let file_name = directory.join("nodes.dmp");
+        info!("Reading taxonomy from file {}", file_name.display());
+        for line in open_file(&file_name)?.lines() {
+            let line = line?;
+            let fields = split_dmp_line(&line);
+            if fields.len() < 3 {
+                continue;
+            }
+            let taxon_id: u32 = fields[0]
+                .parse()
+                .with_context(|| format!("Cannot parse line in nodes.dmp: {}", line))?;
+            let parent_id: u32 = fields[1]
+                .parse()
+                .with_context(|| format!("Cannot parse line in nodes.dmp: {}", line))?;
+            nodes.insert(taxon_id, (parent_id, fields[2].to_string()));
+        }
+
+        let mut names = HashMap::new();
+        let file_name = directory.join("names.dmp");
+        info!("Reading taxon names from file {}", file_name.display());
+        for line in open_file(&file_name)?.lines() {
+            let line = line?;
+            let fields = split_dmp_line(&line);
+            if fields.len() < 4 || fields[3] != "scientific name" {
+                continue;
+            }
+            let taxon_id: u32 = fields[0]
+                .parse()
+                .with_context(|| format!("Cannot parse line in names.dmp: {}", line))?;
+            names.insert(taxon_id, fields[1].to_string());
+        }
+
+        let mut merged = HashMap::new();
+        let file_name = directory.join("merged.dmp");
+        if file_name.exists() {
+            for line in open_file(&file_name)?.lines() {
+                let line = line?;
+                let fields = split_dmp_line(&line);
+                if let (Some(Ok(old_id)), Some(Ok(new_id))) = (
+                    fields.first().map(|value| value.parse::<u32>()),
+                    fields.get(1).map(|value| value.parse::<u32>()),
+                ) {
+                    merged.insert(old_id, new_id);
+                }
+            }
+        }
+
+        info!(
+            "Read {} taxa, with {} names and {} merged",
+            nodes.len(),
+            names.len(),
+            merged.len()
+        );
+
+        Ok(Taxonomy {
+            nodes,
+            names,
+            merged,
+        })
+    }
+
+    /// Returns the current taxon ID, if the one passed was merged
+    fn resolve(&self, taxon_id: u32) -> u32 {
+        self.merged.get(&taxon_id).copied().unwrap_or(taxon_id)
+    }
+
+    /// Returns true if the taxon is in the taxonomy
+    pub fn contains(&self, taxon_id: u32) -> bool {
+        self.nodes.contains_key(&self.resolve(taxon_id))
+    }
+
+    /// Returns the scientific name of a taxon
+    pub fn name(&self, taxon_id: u32) -> Option<&str> {
+        self.names
+            .get(&self.resolve(taxon_id))
+            .map(|name| name.as_str())
+    }
+
+    /// Returns the lineage of a taxon, from the taxon itself to the root
+    /// (excluded). Empty if the taxon is not found.
+    pub fn lineage(&self, taxon_id: u32) -> Vec<u32> {
+        let mut lineage = Vec::new();
+        let mut taxon_id = self.resolve(taxon_id);
+        while taxon_id != ROOT_TAXON {
+            match self.nodes.get(&taxon_id) {
+                None => break,
+                Some((parent_id, _)) => {
+                    lineage.push(taxon_id);
+                    // the root is its own parent, but checked in case of
+                    // incomplete taxonomies
+                    if *parent_id == taxon_id {
+                        break;
+                    }
+                    taxon_id = *parent_id;
+                }
+            }
+        }
+        lineage
+    }
+
+    /// Returns true if the taxon is the same as `ancestor_id` or one of its
+    /// descendants
+    pub fn is_descendant(&self, taxon_id: u32, ancestor_id: u32) -> bool {
+        let ancestor_id = self.resolve(ancestor_id);
+        (ancestor_id == ROOT_TAXON && self.contains(taxon_id))
+            || self.lineage(taxon_id).contains(&ancestor_id)
+    }
+
+    /// Returns the taxon at a rank (e.g. `genus`) in the lineage of a taxon
+    pub fn taxon_at_rank(&self, taxon_id: u32, rank: &str) -> Option<u32> {
+        self.lineage(taxon_id).into_iter().find(|taxon_id| {
+            matches!(self.nodes.get(taxon_id), Some((_, taxon_rank)) if taxon_rank == rank)
+        })
+    }
+}
@@ -1,4 +1,6 @@
 use anyhow::{bail, Result};
+use bio_rascal::gff::Annotation;
+use bio_rascal::taxon::ROOT_TAXON;
 use log::{error, info};
 use std::collections::HashSet;
 use std::fs::File;
@@ -17,7 +19,7 @@ pub fn file_or_stdout(output_file: &Option<PathBuf>) -> Result<Box<dyn Write>> {
         Some(value) => match File::create(value) {
             Err(err) => {
                 error!("Cannot create file {}", value.display());
-                bail!("{}", err.to_string())
+                bail!("{}", err)
             }
             Ok(handle) => {
                 info!("Opening file: {:?}", &output_file);
@@ -41,6 +43,107 @@ pub fn file_or_stdin(input_file: &Option<PathBuf>) -> Result<Box<dyn Read>> {
     Ok(result)
 }
 
+/// Returns the value of a field of an annotation as a `String`.
+///
+/// The built-in fields (`seq_id`, `start`, `uid`, etc.) are checked first,
+/// otherwise the attributes are used. `None` is returned if the attribute
+/// is not present. An unset `taxon_id` is returned as an empty string.
+pub fn get_field_value(annotation: &Annotation, field: &str) -> Option<String> {
+    let value = match field {
+        "uid" => annotation.uid.to_string(),
+        "taxon_id" => match annotation.taxon_id {
+            ROOT_TAXON.. => annotation.taxon_id.to_string(),
+            _ => "".into(),
+        },
+        "seq_id" => annotation.seq_id.clone(),
+        "source" => annotation.source.clone(),
+        "feature_type" => annotation.feature_type.clone(),
+        "start" => annotation.start.to_string(),
+        "end" => annotation.end.to_string(),
+        "score" => annotation.score.to_string(),
+        "strand" => annotation.strand.to_string(),
+        "phase" => annotation.phase.to_string(),
+        "length" => annotation.length().to_string(),
+        _ => return annotation.attributes.get(field).cloned(),
+    };
+    Some(value)
+}
+
+/// Percent-encodes the characters with a special meaning in a GFF3
+/// attribute value (`;`, `=`, `,`, `&`, `%` and control characters), so
+/// free text can be written as is.
+pub fn encode_attribute_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            ';' | '=' | ',' | '&' | '%' => encoded.push_str(&format!("%{:02X}", c as u32)),
+            c if c.is_ascii_control() => encoded.push_str(&format!("%{:02X}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Decodes the percent-encoded characters (`%XX`) of a GFF3 attribute
+/// value. Invalid sequences are kept unchanged.
+pub fn decode_attribute_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%'
+            && index + 2 < bytes.len()
+            && bytes[index + 1].is_ascii_hexdigit()
+            && bytes[index + 2].is_ascii_hexdigit()
+        {
+            let hex = [bytes[index + 1], bytes[index + 2]];
+            let hex = std::str::from_utf8(&hex).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Splits a GFF3 multi-value attribute on the (unescaped) commas and
+/// decodes each value
+pub fn split_attribute_values(value: &str) -> Vec<String> {
+    value.split(',').map(decode_attribute_value).collect()
+}
+
+/// Returns the values of the fields requested, separated by `separator`.
+/// Fields that are missing are skipped.
+pub fn join_field_values(annotation: &Annotation, fields: &[String], separator: &str) -> String {
+    fields
+        .iter()
+        .filter_map(|field| get_field_value(annotation, field))
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Returns the value used to match an annotation to the rows of a table:
+/// the `key` field or, if `prodigal` is true, the `seq_id` and `ID` joined
+/// by `_`, like the sequence names written by Prodigal
+pub fn annotation_key(annotation: &Annotation, key: &str, prodigal: bool) -> Option<String> {
+    if prodigal {
+        annotation
+            .get_attr("ID")
+            .map(|value| format!("{}_{}", annotation.seq_id, value))
+    } else {
+        get_field_value(annotation, key)
+    }
+}
+
+/// Returns true if the annotation is on the minus strand
+pub fn is_minus_strand(annotation: &Annotation) -> bool {
+    annotation.strand.to_string() == "-"
+}
 
 pub fn read_uid_file<P: AsRef<Path>>(uid_file: &Option<P>) -> Result<HashSet<String>> {
     // Makes the set for UIDs